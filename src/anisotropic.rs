@@ -0,0 +1,174 @@
+//! The anisotropic Phong model by Ashikhmin and Shirley.
+//!
+//! A glossy layer, whose highlight can be stretched along the tangent of
+//! the surface, sits on top of a diffuse base. The diffuse base only
+//! receives the light that is not reflected by the glossy layer, so the
+//! model conserves energy. Brushed metals are modelled with a very
+//! different sharpness along the two directions.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    bsdf::{self, Bsdf, BsdfSample},
+    hit_list::HitState,
+    material::around,
+    sampler::{self, Sampler},
+    vec3::Vec3,
+    Color,
+};
+
+/// An anisotropic glossy material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AshikhminShirley {
+    /// Colour of the diffuse base.
+    pub(crate) diffuse: Color,
+    /// Reflectance of the glossy layer at normal incidence.
+    pub(crate) specular: Color,
+    /// Sharpness of the highlight in the direction of the tangent.
+    pub(crate) nu: f32,
+    /// Sharpness of the highlight across the tangent.
+    pub(crate) nv: f32,
+}
+
+impl AshikhminShirley {
+    /// Constructs a new `AshikhminShirley` material. Larger exponents give
+    /// sharper highlights; with `nu == nv` the material is isotropic.
+    pub fn new(diffuse: Color, specular: Color, nu: f32, nv: f32) -> Self {
+        Self {
+            diffuse,
+            specular,
+            nu,
+            nv,
+        }
+    }
+
+    /// Returns the probability of sampling the diffuse base.
+    fn diffuse_probability(&self) -> f32 {
+        let d = self.diffuse.luminance();
+        let s = self.specular.luminance();
+        if d + s <= 0.0 {
+            0.5
+        } else {
+            d / (d + s)
+        }
+    }
+
+    /// Returns the exponent of the highlight for the half vector `h`, which
+    /// has the coordinates `(x, y, z)` in the tangent frame.
+    fn exponent(&self, x: f32, y: f32, z: f32) -> f32 {
+        let sin2 = 1.0 - z * z;
+        if sin2 < 1e-7 {
+            return 0.0;
+        }
+
+        (self.nu * x * x + self.nv * y * y) / sin2
+    }
+
+    /// Returns the density of the half vector with coordinates `(x, y, z)`.
+    fn half_vector_pdf(&self, x: f32, y: f32, z: f32) -> f32 {
+        let norm = ((self.nu + 1.0) * (self.nv + 1.0)).sqrt() / (2.0 * PI);
+        norm * z.powf(self.exponent(x, y, z))
+    }
+}
+
+/// Returns the tangent, bitangent and normal at the hit.
+fn frame(state: &HitState<'_>) -> (Vec3, Vec3, Vec3) {
+    let normal = state.normal;
+    let tangent = state.tangent;
+    (tangent, normal.cross(tangent), normal)
+}
+
+impl Bsdf for AshikhminShirley {
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> Color {
+        let (t, b, n) = frame(state);
+        let n_dot_l = lightv.dot(n);
+        let n_dot_v = eyev.dot(n);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::BLACK;
+        }
+
+        let h = (lightv + eyev).normalize();
+        let k_dot_h = eyev.dot(h);
+        let (x, y, z) = (h.dot(t), h.dot(b), h.dot(n));
+
+        let weight = (1.0 - k_dot_h).powi(5);
+        let fresnel = self.specular * (1.0 - weight) + Color::WHITE * weight;
+        let norm = ((self.nu + 1.0) * (self.nv + 1.0)).sqrt() / (8.0 * PI);
+        let glossy = norm * z.powf(self.exponent(x, y, z)) / (k_dot_h * n_dot_l.max(n_dot_v));
+
+        let fade = |cos: f32| 1.0 - (1.0 - 0.5 * cos).powi(5);
+        let diffuse = self.diffuse.blend(Color::WHITE - self.specular)
+            * (28.0 / (23.0 * PI) * fade(n_dot_l) * fade(n_dot_v));
+
+        diffuse + fresnel * glossy
+    }
+
+    fn sample(
+        &self,
+        state: &HitState<'_>,
+        eyev: Vec3,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let (t, b, n) = frame(state);
+        let (u, v) = sampler.next_2d();
+
+        let lightv = if sampler.next_f32() < self.diffuse_probability() {
+            let (x, y, z) = sampler::sample_cosine_hemisphere(u, v);
+            around(n, x, y, z)
+        } else {
+            // sample the azimuth in the first quadrant and mirror it
+            let quadrant = (4.0 * u).floor().min(3.0);
+            let ratio = ((self.nu + 1.0) / (self.nv + 1.0)).sqrt();
+            let phi = (ratio * (FRAC_PI_2 * (4.0 * u - quadrant)).tan()).atan();
+            let phi = match quadrant as u32 {
+                0 => phi,
+                1 => PI - phi,
+                2 => PI + phi,
+                _ => 2.0 * PI - phi,
+            };
+
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            let e = self.nu * cos_phi * cos_phi + self.nv * sin_phi * sin_phi;
+            let cos = (1.0 - v).powf(1.0 / (e + 1.0));
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+
+            let h = t * (sin * cos_phi) + b * (sin * sin_phi) + n * cos;
+            (-eyev).reflect(h)
+        };
+
+        let pdf = self.pdf(state, lightv, eyev);
+        (pdf > 0.0).then(|| BsdfSample {
+            lightv,
+            value: self.eval(state, lightv, eyev),
+            pdf,
+        })
+    }
+
+    fn pdf(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> f32 {
+        let (t, b, n) = frame(state);
+        let n_dot_l = lightv.dot(n);
+        if n_dot_l <= 0.0 || eyev.dot(n) <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (lightv + eyev).normalize();
+        let k_dot_h = eyev.dot(h);
+        let (x, y, z) = (h.dot(t), h.dot(b), h.dot(n));
+        let glossy = if k_dot_h > 0.0 && z > 0.0 {
+            self.half_vector_pdf(x, y, z) / (4.0 * k_dot_h)
+        } else {
+            0.0
+        };
+
+        let pd = self.diffuse_probability();
+        pd * n_dot_l / PI + (1.0 - pd) * glossy
+    }
+
+    fn albedo(&self, _state: &HitState<'_>) -> Color {
+        self.diffuse
+    }
+
+    fn eq_dyn(&self, other: &dyn Bsdf) -> bool {
+        bsdf::eq_as(self, other)
+    }
+}
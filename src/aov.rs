@@ -0,0 +1,153 @@
+//! Arbitrary output variables (AOVs) of a render.
+//!
+//! Besides the colour, a render can produce images of what the camera rays
+//! hit: the distance to the hit, the normal, position, albedo and texture
+//! coordinates of the surface and the index of the object. Compositors use
+//! them to rework the image, and they make it easy to check the results of
+//! the intersection code.
+//!
+//! The variables are taken from the first hit of the ray through the
+//! centre of every pixel, so they are neither filtered nor blurred by the
+//! lens.
+
+use image::{ImageBuffer, Luma, LumaA, Rgb, Rgb32FImage};
+
+use crate::{
+    bsdf::Bsdf,
+    ray::Ray,
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+/// The output variables of a single pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aovs {
+    /// Distance along the ray to the hit. Infinite if nothing is hit.
+    pub depth: f32,
+    /// Normal of the surface at the hit, facing the camera.
+    pub normal: Vec3,
+    /// Position of the hit.
+    pub position: Point3,
+    /// Colour of the surface at the hit, see [`Bsdf::albedo`].
+    pub albedo: Color,
+    /// Index of the hit object among the objects of the world.
+    pub object: Option<usize>,
+    /// Texture coordinates of the hit.
+    pub uv: (f32, f32),
+}
+
+impl Aovs {
+    /// Returns the output variables where nothing is hit.
+    pub fn background() -> Self {
+        Self {
+            depth: f32::INFINITY,
+            normal: Vec3::default(),
+            position: Point3::default(),
+            albedo: Color::BLACK,
+            object: None,
+            uv: (0.0, 0.0),
+        }
+    }
+
+    /// Returns the output variables of the first hit along the ray.
+    pub fn at(world: &World, r: &Ray) -> Self {
+        let mut xs = world.intersect(r);
+        let state = match xs.prepare_hit(r) {
+            Some(state) => state,
+            None => return Self::background(),
+        };
+
+        Self {
+            depth: state.t * r.dir.mag(),
+            normal: state.normal,
+            position: state.point,
+            albedo: state.obj.material().albedo(&state),
+            object: world
+                .objects
+                .iter()
+                .position(|obj| std::ptr::eq(obj, state.obj)),
+            uv: state.obj.uv_at(state.point),
+        }
+    }
+}
+
+/// The colour image of a render along with its output variables.
+///
+/// The colour is rendered with all the samples of the camera, filtered and
+/// blurred by the lens, while the output variables come from a separate
+/// ray through the centre of every pixel. They do not match the colour
+/// where it is blurred, e.g. at the edges of objects or out of focus.
+#[derive(Debug, Clone)]
+pub struct RenderResult {
+    /// The rendered image.
+    pub color: Rgb32FImage,
+    /// Distance from the camera to the hit.
+    pub depth: ImageBuffer<Luma<f32>, Vec<f32>>,
+    /// Normal of the surface, with the `x`, `y` and `z` coordinates in the
+    /// red, green and blue channels.
+    pub normal: Rgb32FImage,
+    /// Position of the hit, with the `x`, `y` and `z` coordinates in the
+    /// red, green and blue channels.
+    pub position: Rgb32FImage,
+    /// Colour of the material.
+    pub albedo: Rgb32FImage,
+    /// Index of the object, or `-1` where nothing is hit.
+    pub object: ImageBuffer<Luma<f32>, Vec<f32>>,
+    /// Texture coordinates, `u` in the first and `v` in the second channel.
+    pub uv: ImageBuffer<LumaA<f32>, Vec<f32>>,
+}
+
+impl RenderResult {
+    /// Constructs a `RenderResult` for the colour image with the output
+    /// variables of the background everywhere.
+    pub(crate) fn new(color: Rgb32FImage) -> Self {
+        let (width, height) = color.dimensions();
+        let mut result = Self {
+            color,
+            depth: ImageBuffer::new(width, height),
+            normal: Rgb32FImage::new(width, height),
+            position: Rgb32FImage::new(width, height),
+            albedo: Rgb32FImage::new(width, height),
+            object: ImageBuffer::new(width, height),
+            uv: ImageBuffer::new(width, height),
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                result.put(x, y, &Aovs::background());
+            }
+        }
+        result
+    }
+
+    /// Stores the output variables of the pixel at `(x, y)`.
+    pub(crate) fn put(&mut self, x: u32, y: u32, aovs: &Aovs) {
+        let (n, p) = (aovs.normal, aovs.position);
+
+        self.depth.put_pixel(x, y, Luma([aovs.depth]));
+        self.normal.put_pixel(x, y, Rgb([n.x(), n.y(), n.z()]));
+        self.position.put_pixel(x, y, Rgb([p.x(), p.y(), p.z()]));
+        self.albedo.put_pixel(x, y, aovs.albedo.into_inner());
+        self.object
+            .put_pixel(x, y, Luma([aovs.object.map_or(-1.0, |i| i as f32)]));
+        self.uv.put_pixel(x, y, LumaA([aovs.uv.0, aovs.uv.1]));
+    }
+
+    /// Returns the output variables of the pixel at `(x, y)`.
+    pub fn aovs(&self, x: u32, y: u32) -> Aovs {
+        let [nx, ny, nz] = self.normal.get_pixel(x, y).0;
+        let [px, py, pz] = self.position.get_pixel(x, y).0;
+        let object = self.object.get_pixel(x, y).0[0];
+        let [u, v] = self.uv.get_pixel(x, y).0;
+
+        Aovs {
+            depth: self.depth.get_pixel(x, y).0[0],
+            normal: Vec3::new(nx, ny, nz),
+            position: Point3::new(px, py, pz),
+            albedo: self.albedo.get_pixel(x, y).0.into(),
+            object: (object >= 0.0).then_some(object as usize),
+            uv: (u, v),
+        }
+    }
+}
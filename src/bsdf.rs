@@ -0,0 +1,114 @@
+//! The interface between the shading models and the renderer.
+//!
+//! A [`Bsdf`] describes how a surface scatters light. It is evaluated for
+//! the direct lighting of the Whitted integrator and both evaluated and
+//! sampled by the path tracer. The Phong model of [`Material`], the
+//! [`PbrMaterial`], [`OrenNayar`] and [`AshikhminShirley`] implement it,
+//! and custom shading models can be attached to a material with
+//! [`Material::with_bsdf`].
+//!
+//! [`Material`]: crate::material::Material
+//! [`Material::with_bsdf`]: crate::material::Material::with_bsdf
+//! [`PbrMaterial`]: crate::pbr::PbrMaterial
+//! [`OrenNayar`]: crate::oren_nayar::OrenNayar
+//! [`AshikhminShirley`]: crate::anisotropic::AshikhminShirley
+
+use std::{any::Any, f32::consts::PI, fmt::Debug};
+
+use crate::{
+    hit_list::HitState,
+    lights::LightSample,
+    material::around,
+    sampler::{self, Sampler},
+    vec3::Vec3,
+    Color,
+};
+
+/// A direction sampled from a [`Bsdf`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BsdfSample {
+    /// Unit vector pointing from the surface towards where the light arrives.
+    pub lightv: Vec3,
+    /// Value of the BSDF for the direction.
+    pub value: Color,
+    /// Density of the direction with respect to solid angle.
+    pub pdf: f32,
+}
+
+/// A shading model describing how light is scattered at a surface.
+///
+/// All directions are unit vectors pointing away from the surface. Only
+/// `eval()` has to be implemented; the other methods default to cosine
+/// weighted sampling, to shading the point with the BSDF, to the albedo of
+/// a Lambertian surface and to comparing by identity.
+pub trait Bsdf: Any + Debug + Send + Sync {
+    /// Evaluates the BSDF at the hit for light arriving along `lightv` and
+    /// leaving along `eyev`.
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> Color;
+
+    /// Samples a direction for the light arriving at the hit, to be scattered
+    /// towards `eyev`. Returns `None` if the light is absorbed.
+    fn sample(
+        &self,
+        state: &HitState<'_>,
+        eyev: Vec3,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let (u, v) = sampler.next_2d();
+        let (x, y, z) = sampler::sample_cosine_hemisphere(u, v);
+        let lightv = around(state.normal, x, y, z);
+
+        let pdf = self.pdf(state, lightv, eyev);
+        (pdf > 0.0).then(|| BsdfSample {
+            lightv,
+            value: self.eval(state, lightv, eyev),
+            pdf,
+        })
+    }
+
+    /// Returns the density with which `sample()` picks `lightv`.
+    fn pdf(&self, state: &HitState<'_>, lightv: Vec3, _eyev: Vec3) -> f32 {
+        lightv.dot(state.normal).max(0.0) / PI
+    }
+
+    /// Returns the light of a single light sample reflected towards `eyev`,
+    /// as used by the Whitted integrator.
+    ///
+    /// The BSDF is scaled by `π`, so that a white Lambertian surface
+    /// matches a Phong material with a diffuse value of one.
+    fn shade(&self, state: &HitState<'_>, sample: &LightSample, eyev: Vec3) -> Color {
+        let cos = sample.lightv.dot(state.normal);
+        if cos <= 0.0 {
+            return Color::BLACK;
+        }
+
+        self.eval(state, sample.lightv, eyev)
+            .blend(sample.intensity)
+            * (PI * cos)
+    }
+
+    /// Returns the colour of the surface at the hit, as written to the
+    /// albedo output of a render.
+    ///
+    /// By default, this is the reflectance of a Lambertian surface with the
+    /// same value of the BSDF for light arriving and leaving along the
+    /// normal.
+    fn albedo(&self, state: &HitState<'_>) -> Color {
+        self.eval(state, state.normal, state.normal) * PI
+    }
+
+    /// Returns `true` if `other` is the same shading model with the same
+    /// parameters. Materials compare their BSDFs with it.
+    ///
+    /// By default, a BSDF is only equal to itself. Models implementing
+    /// `PartialEq` can compare their parameters with [`eq_as`].
+    fn eq_dyn(&self, other: &dyn Bsdf) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const dyn Bsdf)
+    }
+}
+
+/// Returns `true` if `other` is a `T` equal to `bsdf`. Implements
+/// [`Bsdf::eq_dyn`] for models which implement `PartialEq`.
+pub fn eq_as<T: Bsdf + PartialEq>(bsdf: &T, other: &dyn Bsdf) -> bool {
+    (other as &dyn Any).downcast_ref::<T>() == Some(bsdf)
+}
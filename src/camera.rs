@@ -2,11 +2,117 @@
 //!
 //! The canvas of the camera is always one unit in front of it.
 //! This makes the math cleaner.
+//!
+//! Besides the usual perspective projection, the camera supports orthographic
+//! and panoramic projections, see [`Projection`].
 
-use crate::{matrix::Mat4, ray::Ray, vec3::Point3, world::World};
+use std::{
+    f32::consts::PI,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-/// The `Camera` allows us to look at a scene and render it.
+use crate::{
+    aov::{Aovs, RenderResult},
+    film::Film,
+    filter::Filter,
+    matrix::Mat4,
+    ray::Ray,
+    sampler::{self, Sampler},
+    tile::{self, Tile},
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+/// Describes how points on the canvas are mapped to rays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A pinhole (or thin-lens) perspective projection derived from the
+    /// field of view.
+    Perspective,
+    /// All rays are parallel to the viewing direction. Objects keep their
+    /// size regardless of the distance to the camera.
+    Orthographic {
+        /// Width of the visible area in world-space.
+        view_width: f32,
+    },
+    /// A 360° panorama. The horizontal axis of the canvas maps to the
+    /// longitude and the vertical axis to the latitude.
+    Equirectangular,
+    /// A circular fisheye image whose diameter covers the field of view.
+    /// Points outside the image circle are rendered black.
+    Fisheye(FisheyeMapping),
+}
+
+/// Describes how the angle from the viewing direction maps to the
+/// distance from the centre of a fisheye image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    /// The distance is proportional to the angle.
+    Equidistant,
+    /// Equal areas on the image cover equal solid angles.
+    Equisolid,
+}
+
+/// Describes how far a progressive render has come.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// The pass currently being rendered, starting at zero.
+    pub pass: u32,
+    /// Total number of passes.
+    pub passes: u32,
+    /// Number of tiles finished in the current pass.
+    pub tiles_done: usize,
+    /// Number of tiles in every pass.
+    pub tiles: usize,
+    /// Time since the render started.
+    pub elapsed: Duration,
+    /// Estimated time until the render finishes.
+    pub eta: Duration,
+}
+
+impl Progress {
+    /// Returns the finished fraction of the whole render in `[0, 1]`.
+    pub fn fraction(&self) -> f32 {
+        let total = self.passes as usize * self.tiles;
+        let done = self.pass as usize * self.tiles + self.tiles_done;
+
+        if total == 0 {
+            1.0
+        } else {
+            done as f32 / total as f32
+        }
+    }
+}
+
+/// Events reported by [`Camera::render_progressive`].
+#[derive(Debug)]
+pub enum RenderEvent<'a> {
+    /// A tile of the current pass has been rendered.
+    Tile(Progress),
+    /// A pass has been rendered. Contains the image refined so far.
+    Pass(Progress, &'a image::Rgb32FImage),
+}
+
+/// The result of [`Camera::render_progressive`].
 #[derive(Debug)]
+pub struct ProgressiveImage {
+    /// The image refined by all the passes which were completed.
+    pub image: image::Rgb32FImage,
+    /// Number of passes which were fully rendered.
+    pub passes: u32,
+    /// Whether the render was cancelled before the last pass.
+    pub cancelled: bool,
+}
+
+/// The `Camera` allows us to look at a scene and render it.
+#[derive(Debug, Clone)]
 pub struct Camera {
     /// Width of the canvas in pixels.
     hsize: u32,
@@ -16,6 +122,8 @@ pub struct Camera {
     /// Describes how much the camera can see. When `fov` is small, the
     /// camera is zoomed in on a smaller area of the scene.
     fov: f32,
+    /// How points on the canvas are mapped to rays.
+    projection: Projection,
     /// A view transform describing how the world should be oriented
     /// relative to the camera
     transform: Mat4,
@@ -27,10 +135,31 @@ pub struct Camera {
     half_width: f32,
     /// Height of half of the canvas in world-space.
     half_height: f32,
+    /// Offset of the centre of the canvas from the viewing direction in
+    /// world-space. Shifts the image without tilting the camera.
+    lens_shift: (f32, f32),
+    /// Number of samples taken per pixel.
+    samples: u32,
+    /// Filter used to reconstruct the pixels from the samples.
+    filter: Filter,
+    /// Number of worker threads used for rendering.
+    threads: usize,
+    /// Width and height of the tiles handed to the worker threads.
+    tile_size: u32,
+    /// Radius of the lens in world-space. A radius of zero gives a pinhole
+    /// camera where everything is in focus.
+    aperture: f32,
+    /// Distance from the camera to the plane which is in perfect focus.
+    focal_distance: f32,
+    /// Number of diaphragm blades. The lens is a disk if there are fewer
+    /// than three blades, otherwise it is a regular polygon which gives
+    /// polygonal bokeh.
+    blades: u32,
 }
 
 impl Camera {
     /// Constructs a `Camera`. The default transform is the identity matrix.
+    /// The camera uses a perspective projection.
     pub fn new(hsize: u32, vsize: u32, fov: f32) -> Self {
         let (pixel_size, half_width, half_height) = {
             let half_view = (fov / 2.0).tan();
@@ -50,11 +179,20 @@ impl Camera {
             hsize,
             vsize,
             fov,
+            projection: Projection::Perspective,
             transform: Mat4::identity(),
             transform_inv: Mat4::identity(),
             pixel_size,
             half_width,
             half_height,
+            lens_shift: (0.0, 0.0),
+            samples: 1,
+            filter: Filter::default(),
+            threads: 1,
+            tile_size: 32,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            blades: 0,
         }
     }
 
@@ -65,35 +203,475 @@ impl Camera {
         self
     }
 
-    /// Returns a new ray that starts at the camera and passes through the
-    /// given pixel on the canvas.
+    /// Sets the projection of the camera.
+    /// For fisheye projections, `fov` is the angle covered by the image circle.
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        if let Projection::Orthographic { view_width } = projection {
+            self.half_width = view_width / 2.0;
+            self.pixel_size = view_width / self.hsize as f32;
+            self.half_height = self.pixel_size * self.vsize as f32 / 2.0;
+        } else if self.projection != Projection::Perspective {
+            let Self {
+                hsize, vsize, fov, ..
+            } = self;
+            let perspective = Self::new(hsize, vsize, fov);
+
+            self.half_width = perspective.half_width;
+            self.half_height = perspective.half_height;
+            self.pixel_size = perspective.pixel_size;
+        }
+
+        self.projection = projection;
+        self
+    }
+
+    /// Sets the horizontal and vertical offset of the canvas in world-space.
+    /// A positive horizontal shift moves the canvas to the left of the camera.
+    pub fn with_lens_shift(mut self, dx: f32, dy: f32) -> Self {
+        self.lens_shift = (dx, dy);
+        self
+    }
+
+    /// Sets the number of samples taken per pixel.
+    /// With a single sample, the ray passes through the centre of the pixel.
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples.max(1);
+        self
+    }
+
+    /// Sets the filter used to reconstruct the pixels from the samples.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the number of worker threads used for rendering.
+    /// The image is identical regardless of the number of threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the size of the square tiles the image is split into.
+    /// The image is identical regardless of the tile size.
+    pub fn with_tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// Sets the radius of the lens. Larger apertures give a shallower
+    /// depth of field. Only the perspective projection has a lens.
+    pub fn with_aperture(mut self, aperture: f32) -> Self {
+        self.aperture = aperture.max(0.0);
+        self
+    }
+
+    /// Sets the distance from the camera at which objects are in focus.
+    pub fn with_focal_distance(mut self, focal_distance: f32) -> Self {
+        self.focal_distance = focal_distance;
+        self
+    }
+
+    /// Sets the number of diaphragm blades of the lens.
+    pub fn with_blades(mut self, blades: u32) -> Self {
+        self.blades = blades;
+        self
+    }
+
+    /// Returns the width of the canvas in pixels.
+    pub fn hsize(&self) -> u32 {
+        self.hsize
+    }
+
+    /// Returns the height of the canvas in pixels.
+    pub fn vsize(&self) -> u32 {
+        self.vsize
+    }
+
+    /// Returns the field of view in radians.
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    /// Returns the view transform of the camera.
+    pub fn transform(&self) -> &Mat4 {
+        &self.transform
+    }
+
+    /// Returns the projection of the camera.
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Returns a new ray that starts at the centre of the lens and passes
+    /// through the centre of the given pixel on the canvas.
     pub fn ray_for_pixel(&self, x: u32, y: u32) -> Ray {
-        let x_offset = (x as f32 + 0.5) * self.pixel_size;
-        let y_offset = (y as f32 + 0.5) * self.pixel_size;
+        self.project(x as f32 + 0.5, y as f32 + 0.5, 0.0, 0.0)
+    }
+
+    /// Returns a new ray that passes through the point `(px, py)` on the
+    /// canvas, measured in pixels from the top-left corner.
+    ///
+    /// `lens` is a uniformly distributed sample in `[0, 1)^2` which picks
+    /// the origin of the ray on the lens. The ray is aimed at the point on
+    /// the focal plane seen through `(px, py)`.
+    pub fn ray_for_sample(&self, px: f32, py: f32, lens: (f32, f32)) -> Ray {
+        if self.aperture == 0.0 {
+            return self.project(px, py, 0.0, 0.0);
+        }
+
+        let (u, v) = lens;
+        let (lx, ly) = if self.blades < 3 {
+            sampler::sample_disk(u, v)
+        } else {
+            sampler::sample_polygon(u, v, self.blades)
+        };
+
+        self.project(px, py, lx * self.aperture, ly * self.aperture)
+    }
+
+    /// Returns `true` if the point `(px, py)` on the canvas sees the scene.
+    /// Only the corners of a fisheye image are not covered.
+    pub fn covers(&self, px: f32, py: f32) -> bool {
+        match self.projection {
+            Projection::Fisheye(_) => self.fisheye_radius(px, py).2 <= 1.0,
+            _ => true,
+        }
+    }
+
+    /// Maps the point `(px, py)` on the canvas to a ray according to the
+    /// projection. `(lx, ly)` is the origin of the ray on the lens.
+    fn project(&self, px: f32, py: f32, lx: f32, ly: f32) -> Ray {
+        match self.projection {
+            Projection::Perspective => self.ray_through_lens(px, py, lx, ly),
+            Projection::Orthographic { .. } => {
+                let (world_x, world_y) = self.canvas_point(px, py);
+                let orig = &self.transform_inv * Point3::new(world_x, world_y, 0.);
+                let dir = (&self.transform_inv * Vec3::new(0., 0., -1.)).normalize();
+
+                Ray::new(orig, dir)
+            }
+            Projection::Equirectangular => {
+                let lon = (px / self.hsize as f32 - 0.5) * 2.0 * PI;
+                let lat = (0.5 - py / self.vsize as f32) * PI;
+
+                self.ray_in_direction(Vec3::new(
+                    -lon.sin() * lat.cos(),
+                    lat.sin(),
+                    -lon.cos() * lat.cos(),
+                ))
+            }
+            Projection::Fisheye(mapping) => {
+                let (dx, dy, r) = self.fisheye_radius(px, py);
+                let half_fov = self.fov / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => {
+                        2.0 * (r * (half_fov / 2.0).sin()).clamp(-1.0, 1.0).asin()
+                    }
+                };
+
+                // angle around the viewing direction, +x is to the left
+                let phi = (-dy).atan2(-dx);
+                self.ray_in_direction(Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    -theta.cos(),
+                ))
+            }
+        }
+    }
+
+    /// Returns the offset of `(px, py)` from the centre of a fisheye image
+    /// and its distance from the centre relative to the radius of the image circle.
+    fn fisheye_radius(&self, px: f32, py: f32) -> (f32, f32, f32) {
+        let radius = self.hsize.min(self.vsize) as f32 / 2.0;
+        let dx = (px - self.hsize as f32 / 2.0) / radius;
+        let dy = (py - self.vsize as f32 / 2.0) / radius;
+
+        (dx, dy, (dx * dx + dy * dy).sqrt())
+    }
+
+    /// Returns the ray starting at the camera with the given direction in camera-space.
+    fn ray_in_direction(&self, dir: Vec3) -> Ray {
+        let orig = &self.transform_inv * Point3::default();
+        let dir = (&self.transform_inv * dir).normalize();
+
+        Ray::new(orig, dir)
+    }
+
+    /// Returns the camera-space coordinates of the point `(px, py)` on the canvas.
+    fn canvas_point(&self, px: f32, py: f32) -> (f32, f32) {
+        let x_offset = px * self.pixel_size;
+        let y_offset = py * self.pixel_size;
 
         // camera looks toward -z, so +x is to the left
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
+        let (dx, dy) = self.lens_shift;
+        (
+            self.half_width - x_offset + dx,
+            self.half_height - y_offset + dy,
+        )
+    }
+
+    /// Returns the ray starting at `(lx, ly)` on the lens in camera-space
+    /// and passing through the point `(px, py)` on the canvas.
+    fn ray_through_lens(&self, px: f32, py: f32, lx: f32, ly: f32) -> Ray {
+        let (world_x, world_y) = self.canvas_point(px, py);
 
         // canvas is at z = -1
-        let pixel = &self.transform_inv * Point3::new(world_x, world_y, -1.);
-        let orig = &self.transform_inv * Point3::default();
-        let dir = (pixel - orig).normalize();
+        if lx == 0.0 && ly == 0.0 {
+            let pixel = &self.transform_inv * Point3::new(world_x, world_y, -1.);
+            let orig = &self.transform_inv * Point3::default();
+            let dir = (pixel - orig).normalize();
+
+            return Ray::new(orig, dir);
+        }
+
+        // every ray through the pixel converges on the focal plane
+        let f = self.focal_distance;
+        let focus = &self.transform_inv * Point3::new(world_x * f, world_y * f, -f);
+        let orig = &self.transform_inv * Point3::new(lx, ly, 0.);
+        let dir = (focus - orig).normalize();
 
         Ray::new(orig, dir)
     }
 
     /// Renders the given scene to the image.
+    ///
+    /// The image is split into tiles which are rendered on the worker
+    /// threads. The result does not depend on the number of threads or the
+    /// size of the tiles, see `render_pass()`.
     pub fn render(&self, world: &World) -> image::Rgb32FImage {
-        let mut canvas = image::Rgb32FImage::new(self.hsize, self.vsize);
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&ray);
-                canvas.put_pixel(x, y, color.into_inner());
+        let (film, _) = self
+            .render_pass(
+                world,
+                0..self.samples,
+                &|_, _| (),
+                &AtomicBool::new(false),
+                &|| {},
+            )
+            .expect("the render is never cancelled");
+        film.to_image()
+    }
+
+    /// Renders the given scene along with its arbitrary output variables.
+    ///
+    /// The variables are taken from a separate ray through the centre of
+    /// every pixel, so they do not follow the filtering and the depth of
+    /// field of the colour. Pixels outside the image circle of a fisheye
+    /// projection get the variables of the background.
+    pub fn render_aovs(&self, world: &World) -> RenderResult {
+        let aov = |x: u32, y: u32| {
+            if self.covers(x as f32 + 0.5, y as f32 + 0.5) {
+                Aovs::at(world, &self.ray_for_pixel(x, y))
+            } else {
+                Aovs::background()
+            }
+        };
+        let (film, aovs) = self
+            .render_pass(
+                world,
+                0..self.samples,
+                &aov,
+                &AtomicBool::new(false),
+                &|| {},
+            )
+            .expect("the render is never cancelled");
+
+        let mut result = RenderResult::new(film.to_image());
+        for (i, aovs) in aovs.iter().enumerate() {
+            let i = i as u32;
+            result.put(i % self.hsize, i / self.hsize, aovs);
+        }
+
+        result
+    }
+
+    /// Renders the scene progressively, one sample per pixel at a time.
+    ///
+    /// Every pass adds one sample to each pixel, so that a noisy preview is
+    /// available early and refined until all the samples have been taken.
+    /// `on_event` is called on the calling thread after every tile and pass.
+    ///
+    /// Setting `cancel` stops the render once the tiles being worked on
+    /// are finished. The returned image is then the one of the last pass
+    /// which was completed; the tiles of the unfinished pass are discarded.
+    pub fn render_progressive<F>(
+        &self,
+        world: &World,
+        cancel: &AtomicBool,
+        mut on_event: F,
+    ) -> ProgressiveImage
+    where
+        F: FnMut(RenderEvent<'_>),
+    {
+        let start = Instant::now();
+        let tiles = Tile::split(self.hsize, self.vsize, self.tile_size).len();
+        let passes = self.samples;
+
+        let mut film = Film::new(0, 0, self.hsize, self.vsize);
+        let mut image = image::Rgb32FImage::new(self.hsize, self.vsize);
+
+        for pass in 0..passes {
+            let progress = |tiles_done| {
+                let mut progress = Progress {
+                    pass,
+                    passes,
+                    tiles_done,
+                    tiles,
+                    elapsed: start.elapsed(),
+                    eta: Duration::ZERO,
+                };
+
+                let fraction = progress.fraction();
+                if fraction > 0.0 {
+                    progress.eta = progress.elapsed.mul_f32((1.0 - fraction) / fraction);
+                }
+                progress
+            };
+
+            let pass_film = thread::scope(|scope| {
+                let (tx, rx) = mpsc::channel();
+                let worker = scope.spawn(move || {
+                    self.render_pass(world, pass..pass + 1, &|_, _| (), cancel, &|| {
+                        let _ = tx.send(());
+                    })
+                });
+
+                // the channel is closed once the workers have finished the pass
+                for (i, ()) in rx.into_iter().enumerate() {
+                    on_event(RenderEvent::Tile(progress(i + 1)));
+                }
+
+                worker.join().expect("render thread panicked")
+            });
+
+            // a partial pass would leave the tiles with different numbers
+            // of samples, so it is discarded
+            let pass_film = match pass_film {
+                Some((pass_film, _)) => pass_film,
+                None => {
+                    return ProgressiveImage {
+                        image,
+                        passes: pass,
+                        cancelled: true,
+                    }
+                }
+            };
+
+            film.merge(&pass_film);
+            image = film.to_image();
+
+            on_event(RenderEvent::Pass(progress(tiles), &image));
+        }
+
+        ProgressiveImage {
+            image,
+            passes,
+            cancelled: false,
+        }
+    }
+
+    /// Renders the given range of samples of every pixel into a film
+    /// covering the whole image, along with `aov(x, y)` of every pixel in
+    /// scanline order.
+    ///
+    /// The tiles are rendered on the worker threads one row of tiles at a
+    /// time, and `tile_done` is called after each of them. Every pixel
+    /// splats its samples onto a film of its own, and these are merged in
+    /// the scanline order of the pixels. As the samples are also seeded
+    /// per pixel, the result is the same whatever the number of threads
+    /// and the size of the tiles.
+    ///
+    /// Returns `None` if `cancel` is set before all the tiles are rendered.
+    fn render_pass<A: Send>(
+        &self,
+        world: &World,
+        samples: Range<u32>,
+        aov: &(dyn Fn(u32, u32) -> A + Sync),
+        cancel: &AtomicBool,
+        tile_done: &(dyn Fn() + Sync),
+    ) -> Option<(Film, Vec<A>)> {
+        let tiles = Tile::split(self.hsize, self.vsize, self.tile_size);
+        let mut film = Film::new(0, 0, self.hsize, self.vsize);
+        let mut aovs = Vec::with_capacity((self.hsize * self.vsize) as usize);
+
+        // only the films of the pixels of one row of tiles are kept at once
+        for row in tiles.chunk_by(|a, b| a.y0 == b.y0) {
+            let pixels = tile::for_each_parallel(row, self.threads, |tile| {
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+
+                let pixels = self.render_tile(world, tile, samples.clone(), aov);
+                tile_done();
+                Some(pixels.into_iter())
+            });
+            let mut pixels = pixels.into_iter().collect::<Option<Vec<_>>>()?;
+
+            for _ in 0..row[0].height {
+                for (tile, pixels) in row.iter().zip(&mut pixels) {
+                    for (pixel, pixel_aov) in pixels.by_ref().take(tile.width as usize) {
+                        film.merge(&pixel);
+                        aovs.push(pixel_aov);
+                    }
+                }
             }
         }
 
-        canvas
+        Some((film, aovs))
+    }
+
+    /// Renders the given range of samples of the pixels in `tile`, in
+    /// scanline order, along with `aov(x, y)` of every pixel. Every pixel
+    /// gets a film of its own, which also covers the neighbouring pixels
+    /// reached by the filter.
+    fn render_tile<A>(
+        &self,
+        world: &World,
+        tile: &Tile,
+        samples: Range<u32>,
+        aov: &dyn Fn(u32, u32) -> A,
+    ) -> Vec<(Film, A)> {
+        tile.pixels()
+            .map(|(x, y)| {
+                let mut film = self.pixel_film(x, y);
+                for s in samples.clone() {
+                    let mut sampler = Sampler::for_pixel(x, y, s);
+                    let (px, py) = self.sample_position(x, y, &mut sampler);
+                    let lens = sampler.next_2d();
+                    let color = if self.covers(px, py) {
+                        world.color_at_sampled(&self.ray_for_sample(px, py, lens), &mut sampler)
+                    } else {
+                        Color::BLACK
+                    };
+                    film.add_sample(px, py, color, &self.filter);
+                }
+                (film, aov(x, y))
+            })
+            .collect()
+    }
+
+    /// Returns an empty film covering the pixel at `(x, y)` and the pixels
+    /// around it reached by the filter.
+    fn pixel_film(&self, x: u32, y: u32) -> Film {
+        let pad = (self.filter.radius() + 0.5).ceil() as u32;
+
+        let x0 = x.saturating_sub(pad);
+        let y0 = y.saturating_sub(pad);
+        let x1 = (x + 1 + pad).min(self.hsize);
+        let y1 = (y + 1 + pad).min(self.vsize);
+
+        Film::new(x0, y0, x1 - x0, y1 - y0)
+    }
+
+    /// Returns the position on the canvas of the given sample of a pixel.
+    fn sample_position(&self, x: u32, y: u32, sampler: &mut Sampler) -> (f32, f32) {
+        if self.samples == 1 {
+            return (x as f32 + 0.5, y as f32 + 0.5);
+        }
+
+        let (u, v) = sampler.next_2d();
+        (x as f32 + u, y as f32 + v)
     }
 }
@@ -0,0 +1,123 @@
+//! Accumulates filtered samples into pixels.
+//!
+//! Each sample is splatted onto every pixel within the radius of the
+//! reconstruction filter. A pixel stores the weighted sum of the colours
+//! and the sum of the weights so that the final colour can be normalized.
+
+use crate::{filter::Filter, Color};
+
+/// A rectangular region of the image which accumulates samples.
+#[derive(Debug, Clone)]
+pub(crate) struct Film {
+    /// Column of the top-left pixel of the region.
+    x0: u32,
+    /// Row of the top-left pixel of the region.
+    y0: u32,
+    /// Width of the region in pixels.
+    width: u32,
+    /// Height of the region in pixels.
+    height: u32,
+    /// Weighted sum of the colours of every pixel.
+    sum: Vec<[f32; 3]>,
+    /// Sum of the weights of every pixel.
+    weight: Vec<f32>,
+}
+
+impl Film {
+    /// Constructs an empty `Film` covering `width x height` pixels
+    /// starting at `(x0, y0)`.
+    pub(crate) fn new(x0: u32, y0: u32, width: u32, height: u32) -> Self {
+        let len = (width * height) as usize;
+
+        Self {
+            x0,
+            y0,
+            width,
+            height,
+            sum: vec![[0.0; 3]; len],
+            weight: vec![0.0; len],
+        }
+    }
+
+    /// Adds a sample at the continuous raster position `(px, py)`.
+    /// Pixels outside the region are ignored.
+    pub(crate) fn add_sample(&mut self, px: f32, py: f32, color: Color, filter: &Filter) {
+        let radius = filter.radius();
+        let color = color.into_inner();
+
+        // pixel centres are at half-integer coordinates
+        let min_x = (px - 0.5 - radius).ceil().max(self.x0 as f32) as u32;
+        let min_y = (py - 0.5 - radius).ceil().max(self.y0 as f32) as u32;
+        let max_x = (px - 0.5 + radius).floor();
+        let max_y = (py - 0.5 + radius).floor();
+
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+
+        let max_x = (max_x as u32).min(self.x0 + self.width - 1);
+        let max_y = (max_y as u32).min(self.y0 + self.height - 1);
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let w = filter.eval(px - (x as f32 + 0.5), py - (y as f32 + 0.5));
+                if w == 0.0 {
+                    continue;
+                }
+
+                let i = self.index(x, y);
+                for c in 0..3 {
+                    self.sum[i][c] += color[c] * w;
+                }
+                self.weight[i] += w;
+            }
+        }
+    }
+
+    /// Adds the accumulated values of `other` onto `self`.
+    /// The parts of `other` lying outside `self` are ignored.
+    pub(crate) fn merge(&mut self, other: &Film) {
+        for y in other.y0..other.y0 + other.height {
+            for x in other.x0..other.x0 + other.width {
+                if !self.contains(x, y) {
+                    continue;
+                }
+
+                let (i, j) = (self.index(x, y), other.index(x, y));
+                for c in 0..3 {
+                    self.sum[i][c] += other.sum[j][c];
+                }
+                self.weight[i] += other.weight[j];
+            }
+        }
+    }
+
+    /// Returns the normalized colour of the pixel at `(x, y)`.
+    pub(crate) fn pixel(&self, x: u32, y: u32) -> Color {
+        let i = self.index(x, y);
+        let w = self.weight[i];
+
+        if w == 0.0 {
+            Color::BLACK
+        } else {
+            let [r, g, b] = self.sum[i];
+            [r / w, g / w, b / w].into()
+        }
+    }
+
+    /// Resolves the region into an image of its own size.
+    pub(crate) fn to_image(&self) -> image::Rgb32FImage {
+        image::Rgb32FImage::from_fn(self.width, self.height, |x, y| {
+            self.pixel(self.x0 + x, self.y0 + y).into_inner()
+        })
+    }
+
+    fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x0..self.x0 + self.width).contains(&x)
+            && (self.y0..self.y0 + self.height).contains(&y)
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y - self.y0) * self.width + (x - self.x0)) as usize
+    }
+}
@@ -0,0 +1,143 @@
+//! Reconstruction filters used to combine the samples taken within and
+//! around a pixel into its final colour.
+//!
+//! All filters are separable, i.e. the 2d weight is the product of the
+//! 1d weights along each axis. The radius is given in pixels and may be
+//! larger than half a pixel, in which case a sample also contributes to
+//! the neighbouring pixels.
+
+use std::f32::consts::PI;
+
+/// A pixel reconstruction filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Every sample within the radius has the same weight.
+    Box {
+        /// Radius of the filter in pixels.
+        radius: f32,
+    },
+    /// The weight falls off linearly with the distance from the pixel centre.
+    Tent {
+        /// Radius of the filter in pixels.
+        radius: f32,
+    },
+    /// A Gaussian bump, shifted so that it reaches zero at the radius.
+    Gaussian {
+        /// Radius of the filter in pixels.
+        radius: f32,
+        /// Falloff rate of the Gaussian. Larger values give a sharper filter.
+        alpha: f32,
+    },
+    /// The Mitchell-Netravali cubic filter.
+    Mitchell {
+        /// Radius of the filter in pixels.
+        radius: f32,
+        /// The `B` parameter of the cubic.
+        b: f32,
+        /// The `C` parameter of the cubic.
+        c: f32,
+    },
+    /// A sinc filter windowed by a wider sinc.
+    Lanczos {
+        /// Radius of the filter in pixels.
+        radius: f32,
+        /// Number of sinc cycles within the window.
+        tau: f32,
+    },
+}
+
+impl Filter {
+    /// Constructs a `Box` filter.
+    pub fn new_box(radius: f32) -> Self {
+        Self::Box { radius }
+    }
+
+    /// Constructs a `Tent` filter.
+    pub fn new_tent(radius: f32) -> Self {
+        Self::Tent { radius }
+    }
+
+    /// Constructs a `Gaussian` filter.
+    pub fn new_gaussian(radius: f32, alpha: f32) -> Self {
+        Self::Gaussian { radius, alpha }
+    }
+
+    /// Constructs a `Mitchell` filter.
+    /// `b = c = 1/3` is the usual recommendation.
+    pub fn new_mitchell(radius: f32, b: f32, c: f32) -> Self {
+        Self::Mitchell { radius, b, c }
+    }
+
+    /// Constructs a `Lanczos` filter.
+    pub fn new_lanczos(radius: f32, tau: f32) -> Self {
+        Self::Lanczos { radius, tau }
+    }
+
+    /// Returns the radius of the filter in pixels.
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    /// Returns the weight of a sample offset by `(dx, dy)` pixels from
+    /// the centre of a pixel. The weight may be negative.
+    pub fn eval(&self, dx: f32, dy: f32) -> f32 {
+        self.eval_1d(dx) * self.eval_1d(dy)
+    }
+
+    fn eval_1d(&self, x: f32) -> f32 {
+        let x = x.abs();
+        let radius = self.radius();
+        if x > radius {
+            return 0.0;
+        }
+
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { radius } => radius - x,
+            Self::Gaussian { radius, alpha } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Self::Mitchell { radius, b, c } => mitchell_1d(2.0 * x / radius, b, c),
+            Self::Lanczos { tau, .. } => sinc(x) * sinc(x / tau),
+        }
+    }
+}
+
+impl Default for Filter {
+    /// A box filter covering exactly one pixel.
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+/// The Mitchell-Netravali cubic for `x` in `[0, 2]`.
+fn mitchell_1d(x: f32, b: f32, c: f32) -> f32 {
+    let p = if x > 1.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    };
+
+    p / 6.0
+}
+
+/// The normalized sinc function.
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        let px = PI * x;
+        px.sin() / px
+    }
+}
@@ -9,6 +9,10 @@ use crate::{
     vec3::{Point3, Vec3},
 };
 
+/// Distance by which points are nudged off a surface to keep secondary rays
+/// from intersecting the surface they start on.
+pub const EPSILON: f32 = 0.005;
+
 /// Stores data related to intersections.
 #[derive(Debug, PartialEq)]
 pub struct HitRec<'a> {
@@ -21,7 +25,7 @@ pub struct HitRec<'a> {
 impl<'a> HitRec<'a> {
     /// Constructs a `HitState` to make it easier to reuse computations
     /// for an intersection.
-    pub fn prepare_computations(&self, r: &Ray) -> HitState<'_> {
+    pub fn prepare_computations(&self, r: &Ray) -> HitState<'a> {
         let t = self.t;
         let obj = self.obj;
         let point = r.pos(t);
@@ -35,13 +39,23 @@ impl<'a> HitRec<'a> {
             false
         };
 
+        // assume the object is not nested inside another one
+        let index = obj.material().refractive_index;
+        let (n1, n2) = if inside { (index, 1.0) } else { (1.0, index) };
+
         HitState {
             t,
             obj,
             point,
+            over_point: point + normal * EPSILON,
+            under_point: point - normal * EPSILON,
             eyev,
             normal,
+            tangent: obj.tangent_at(point),
             inside,
+            n1,
+            n2,
+            within: inside.then_some(obj),
         }
     }
 }
@@ -70,9 +84,54 @@ impl<'a> HitList<'a> {
     }
 
     /// Returns the record with the smallest non-negative `t`.
+    /// The boundaries of participating media are invisible and skipped.
     pub fn hit(&mut self) -> Option<&HitRec<'_>> {
         self.sort();
-        self.inner.iter().find(|h| h.t >= 0.0)
+        self.inner
+            .iter()
+            .find(|h| h.t >= 0.0 && h.obj.medium().is_none())
+    }
+
+    /// Prepares the computations for the hit like
+    /// `HitRec::prepare_computations()`, but takes nested objects into
+    /// account: the refractive indices on both sides of the surface and the
+    /// object the ray travelled through are found by walking through all the
+    /// intersections up to the hit.
+    pub fn prepare_hit(&mut self, r: &Ray) -> Option<HitState<'a>> {
+        self.sort();
+        let index = self
+            .inner
+            .iter()
+            .position(|h| h.t >= 0.0 && h.obj.medium().is_none())?;
+        let mut state = self.inner[index].prepare_computations(r);
+
+        let mut containers: Vec<&Sphere> = Vec::new();
+        let refractive_index =
+            |c: &[&Sphere]| c.last().map_or(1.0, |obj| obj.material().refractive_index);
+        for (i, h) in self.inner.iter().enumerate() {
+            if h.obj.medium().is_some() {
+                continue;
+            }
+
+            if i == index {
+                state.n1 = refractive_index(&containers);
+                state.within = containers.last().copied();
+            }
+
+            match containers.iter().position(|obj| std::ptr::eq(*obj, h.obj)) {
+                Some(pos) => {
+                    containers.remove(pos);
+                }
+                None => containers.push(h.obj),
+            }
+
+            if i == index {
+                state.n2 = refractive_index(&containers);
+                break;
+            }
+        }
+
+        Some(state)
     }
 
     /// Returns the number of `HitRec`s stored.
@@ -110,10 +169,65 @@ pub struct HitState<'a> {
     pub obj: &'a Sphere,
     /// Point of intersection.
     pub point: Point3,
+    /// Point of intersection moved slightly along the normal.
+    /// Used as the origin of shadow rays to avoid self-intersection.
+    pub over_point: Point3,
+    /// Point of intersection moved slightly below the surface.
+    /// Used as the origin of refracted rays.
+    pub under_point: Point3,
     /// Eye vector.
     pub eyev: Vec3,
     /// Normal at the intersection.
     pub normal: Vec3,
+    /// Unit tangent at the intersection, perpendicular to the normal.
+    /// Orients anisotropic materials.
+    pub tangent: Vec3,
     /// Whether the hit occurred inside an object.
     pub inside: bool,
+    /// Refractive index on the side of the surface the ray comes from.
+    pub n1: f32,
+    /// Refractive index on the other side of the surface.
+    pub n2: f32,
+    /// The innermost object the ray travelled through to reach the hit.
+    pub within: Option<&'a Sphere>,
+}
+
+impl HitState<'_> {
+    /// Returns the direction of the ray reflected at the surface.
+    pub fn reflectv(&self) -> Vec3 {
+        (-self.eyev.normalize()).reflect(self.normal)
+    }
+
+    /// Returns the direction of the ray refracted at the surface, or `None`
+    /// if the light is totally reflected.
+    pub fn refractv(&self) -> Option<Vec3> {
+        let eyev = self.eyev.normalize();
+        let ratio = self.n1 / self.n2;
+        let cos_i = eyev.dot(self.normal);
+        let sin2_t = ratio * ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(self.normal * (ratio * cos_i - cos_t) - eyev * ratio)
+    }
+
+    /// Returns the fraction of the light reflected at the surface, using
+    /// Schlick's approximation of the Fresnel equations.
+    pub fn schlick(&self) -> f32 {
+        let mut cos = self.eyev.normalize().dot(self.normal);
+
+        if self.n1 > self.n2 {
+            let ratio = self.n1 / self.n2;
+            let sin2_t = ratio * ratio * (1.0 - cos * cos);
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            cos = (1.0 - sin2_t).sqrt();
+        }
+
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
 }
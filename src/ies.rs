@@ -0,0 +1,241 @@
+//! Parser for IESNA LM-63 photometric data files (`.ies`).
+//!
+//! A profile describes the luminous intensity of a real fixture in every
+//! direction, given as a grid of candela values over vertical and
+//! horizontal angles. The vertical angle is measured from the nadir, the
+//! direction the fixture points at, and the horizontal angle around it.
+//!
+//! Only the type C photometry used by architectural fixtures is supported;
+//! files with type A or B photometry are rejected. Lamp tilt data is
+//! skipped.
+
+use std::{error, fmt, fs, io, path::Path};
+
+use crate::vec3::Vec3;
+
+/// Errors which can occur while reading an `.ies` file.
+#[derive(Debug)]
+pub enum IesError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The `TILT=` line which precedes the photometric data is missing.
+    MissingTilt,
+    /// A value could not be parsed as a number.
+    InvalidNumber(String),
+    /// The file ended before all the values were read.
+    UnexpectedEnd,
+    /// The number of angles is zero or the angles are not increasing.
+    InvalidAngles,
+    /// The file uses type A or B photometry instead of type C.
+    UnsupportedPhotometry(f32),
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read IES file: {}", e),
+            Self::MissingTilt => write!(f, "missing TILT= line"),
+            Self::InvalidNumber(s) => write!(f, "invalid number `{}`", s),
+            Self::UnexpectedEnd => write!(f, "unexpected end of file"),
+            Self::InvalidAngles => write!(f, "invalid angles"),
+            Self::UnsupportedPhotometry(t) => write!(f, "unsupported photometric type {}", t),
+        }
+    }
+}
+
+impl error::Error for IesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for IesError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// The candela distribution of a light fixture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    /// Vertical angles in degrees, increasing.
+    vertical: Vec<f32>,
+    /// Horizontal angles in degrees, increasing.
+    horizontal: Vec<f32>,
+    /// Candela values, one row of vertical angles per horizontal angle.
+    candela: Vec<f32>,
+    /// The largest candela value.
+    max_candela: f32,
+}
+
+impl IesProfile {
+    /// Reads the profile from an `.ies` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IesError> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text)
+    }
+
+    /// Parses the contents of an `.ies` file.
+    pub fn parse(text: &str) -> Result<Self, IesError> {
+        // everything up to the TILT line is free-form header
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find_map(|l| l.strip_prefix("TILT="))
+            .ok_or(IesError::MissingTilt)?;
+
+        let rest: Vec<&str> = lines.collect();
+        let mut values = rest
+            .iter()
+            .flat_map(|l| l.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<f32>()
+                    .map_err(|_| IesError::InvalidNumber(s.into()))
+            });
+        let mut next = || values.next().unwrap_or(Err(IesError::UnexpectedEnd));
+
+        if tilt.trim() == "INCLUDE" {
+            // lamp-to-luminaire geometry, then the tilt angles and multipliers
+            next()?;
+            let count = next()? as usize;
+            for _ in 0..count {
+                next()?;
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let num_vertical = next()? as usize;
+        let num_horizontal = next()? as usize;
+        let num_candela = num_vertical
+            .checked_mul(num_horizontal)
+            .ok_or(IesError::InvalidAngles)?;
+
+        // only type C photometry is supported
+        let photometric_type = next()?;
+        if photometric_type != 1.0 {
+            return Err(IesError::UnsupportedPhotometry(photometric_type));
+        }
+        // units, width, length, height
+        for _ in 0..4 {
+            next()?;
+        }
+        let ballast = next()?;
+        // ballast-lamp photometric factor and input watts
+        next()?;
+        next()?;
+
+        let mut read = |n: usize| (0..n).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical = read(num_vertical)?;
+        let horizontal = read(num_horizontal)?;
+        let candela: Vec<f32> = read(num_candela)?
+            .into_iter()
+            .map(|c| c * multiplier * ballast)
+            .collect();
+
+        let increasing = |a: &[f32]| !a.is_empty() && a.windows(2).all(|w| w[0] < w[1]);
+        if !increasing(&vertical) || !increasing(&horizontal) {
+            return Err(IesError::InvalidAngles);
+        }
+
+        let max_candela = candela.iter().copied().fold(0.0, f32::max);
+
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela,
+            max_candela,
+        })
+    }
+
+    /// Returns the largest candela value of the profile.
+    pub fn max_candela(&self) -> f32 {
+        self.max_candela
+    }
+
+    /// Returns the interpolated candela value at the given vertical and
+    /// horizontal angles in degrees. The horizontal symmetry of the
+    /// profile is taken into account.
+    pub fn candela(&self, vertical: f32, horizontal: f32) -> f32 {
+        let h = self.fold_horizontal(horizontal.rem_euclid(360.0));
+
+        let (v0, v1, tv) = match bracket(&self.vertical, vertical) {
+            Some(b) => b,
+            None => return 0.0,
+        };
+        let (h0, h1, th) = bracket(&self.horizontal, h).unwrap_or((0, 0, 0.0));
+
+        let n = self.vertical.len();
+        let at = |h: usize, v: usize| self.candela[h * n + v];
+
+        let c0 = at(h0, v0) * (1.0 - tv) + at(h0, v1) * tv;
+        let c1 = at(h1, v0) * (1.0 - tv) + at(h1, v1) * tv;
+        c0 * (1.0 - th) + c1 * th
+    }
+
+    /// Returns the fraction of the peak intensity emitted along `dir`.
+    ///
+    /// `nadir` is the unit vector the fixture points at and `reference`
+    /// a unit vector perpendicular to it marking the horizontal angle zero.
+    pub fn factor(&self, dir: Vec3, nadir: Vec3, reference: Vec3) -> f32 {
+        if self.max_candela <= 0.0 {
+            return 0.0;
+        }
+
+        let side = nadir.cross(reference);
+        let vertical = dir.dot(nadir).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = dir.dot(side).atan2(dir.dot(reference)).to_degrees();
+
+        self.candela(vertical, horizontal) / self.max_candela
+    }
+
+    /// Maps a horizontal angle in `[0, 360)` onto the range covered by the
+    /// profile, using the symmetry implied by the last horizontal angle.
+    fn fold_horizontal(&self, h: f32) -> f32 {
+        let last = *self.horizontal.last().unwrap_or(&0.0);
+
+        if last == 0.0 {
+            // rotationally symmetric
+            0.0
+        } else if last == 90.0 {
+            // symmetric in each quadrant
+            match h {
+                h if h > 270.0 => 360.0 - h,
+                h if h > 180.0 => h - 180.0,
+                h if h > 90.0 => 180.0 - h,
+                h => h,
+            }
+        } else if last == 180.0 && h > 180.0 {
+            // bilaterally symmetric
+            360.0 - h
+        } else {
+            h
+        }
+    }
+}
+
+/// Finds the two entries of the increasing `angles` surrounding `x` and the
+/// interpolation weight between them. Returns `None` if `x` lies outside.
+fn bracket(angles: &[f32], x: f32) -> Option<(usize, usize, f32)> {
+    let first = *angles.first()?;
+    let last = *angles.last()?;
+
+    if x < first || x > last {
+        return None;
+    }
+    if angles.len() == 1 || x == last {
+        let i = angles.len() - 1;
+        return Some((i, i, 0.0));
+    }
+
+    let i = angles.partition_point(|&a| a <= x) - 1;
+    let t = (x - angles[i]) / (angles[i + 1] - angles[i]);
+    Some((i, i + 1, t))
+}
@@ -0,0 +1,480 @@
+//! Integrators compute the colour seen along a ray.
+//!
+//! The default is the Whitted-style shader of [`World::shade_hit`], which
+//! only accounts for light arriving directly from the light sources and
+//! fakes everything else with a constant ambient term. The path tracer
+//! follows the light as it bounces between surfaces to produce global
+//! illumination. Ambient occlusion renders how much of the surroundings of
+//! every hit is open, without any lighting.
+
+use crate::{
+    bsdf::Bsdf,
+    hit_list::HitState,
+    lights::{AreaLight, Light, LightSample},
+    material::around,
+    medium::Medium,
+    ray::Ray,
+    sampler::{self, Sampler},
+    sphere::Sphere,
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+/// Selects how the colour along a ray is computed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Integrator {
+    /// Direct lighting with the Phong model and a constant ambient term,
+    /// following the rays reflected and refracted by transparent objects.
+    #[default]
+    Whitted,
+    /// Unbiased Monte Carlo path tracing.
+    PathTracer(PathTracer),
+    /// Grey ambient occlusion without any lighting.
+    AmbientOcclusion(AmbientOcclusion),
+}
+
+/// A unidirectional path tracer.
+///
+/// At every bounce, the light sources are sampled directly (next-event
+/// estimation) and the path continues in a direction drawn from the BRDF.
+/// Inside participating media, the distance to the next scattering event
+/// is sampled and the path continues in a direction drawn from the phase
+/// function. At transparent surfaces, the path is reflected or refracted
+/// perfectly instead, with a probability given by the transparency.
+/// Area lights can be reached by both strategies, so their contributions
+/// are combined with multiple importance sampling using the power
+/// heuristic. Paths are terminated randomly with Russian roulette once they
+/// get long, which keeps the estimate unbiased.
+///
+/// Each camera sample traces a single path; the number of samples per
+/// pixel is set with [`Camera::with_samples`](crate::camera::Camera::with_samples).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTracer {
+    /// Maximum number of surfaces a path may hit.
+    pub(crate) max_depth: u32,
+    /// Number of bounces after which Russian roulette starts.
+    pub(crate) rr_depth: u32,
+}
+
+impl PathTracer {
+    /// Constructs a new `PathTracer` with at most `max_depth` bounces.
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            max_depth,
+            rr_depth: 3,
+        }
+    }
+
+    /// Sets the number of bounces after which Russian roulette starts.
+    pub fn with_rr_depth(mut self, rr_depth: u32) -> Self {
+        self.rr_depth = rr_depth;
+        self
+    }
+
+    /// Returns the radiance arriving along the ray.
+    pub fn radiance(&self, world: &World, r: &Ray, sampler: &mut Sampler) -> Color {
+        let mut radiance = Color::BLACK;
+        let mut throughput = Color::WHITE;
+        let mut ray = r.clone();
+        // density of the BRDF or phase function sample which produced the
+        // ray, if any
+        let mut brdf_pdf = None;
+
+        for depth in 0..=self.max_depth {
+            let mut xs = world.intersect(&ray);
+            let state = xs.prepare_hit(&ray);
+            let hit_t = state.as_ref().map_or(f32::INFINITY, |state| state.t);
+            let light_hit = hit_light(world, &ray, hit_t);
+            let t_end = light_hit.map_or(hit_t, |(_, t)| t);
+
+            // the light may be scattered by a medium before reaching the
+            // surface or the light
+            if let Some((t, medium)) = sample_media(world, &ray, t_end, &mut throughput, sampler) {
+                let point = ray.pos(t);
+                let eyev = -ray.dir.normalize();
+                let vertex = Vertex::Medium {
+                    point,
+                    eyev,
+                    medium: &medium,
+                };
+                radiance = radiance + throughput.blend(direct_lighting(world, &vertex, sampler));
+
+                if depth == self.max_depth {
+                    break;
+                }
+
+                // the phase function is sampled exactly, so the throughput
+                // does not change
+                let dir = medium.sample_phase(eyev, sampler);
+                brdf_pdf = Some(medium.phase(dir, eyev));
+                if !self.survives(depth, &mut throughput, sampler) {
+                    break;
+                }
+
+                ray = Ray::new(point, dir);
+                continue;
+            }
+
+            if let Some((light, t)) = light_hit {
+                let weight = brdf_pdf.map_or(1.0, |pdf| {
+                    let v = ray.dir * t;
+                    let distance = v.mag();
+                    let light_pdf = light.pdf(v / distance, distance);
+                    power_heuristic(1, pdf, light.sample_count(), light_pdf)
+                });
+
+                radiance = radiance + throughput.blend(light.radiance()) * weight;
+                break;
+            }
+
+            let state = match state {
+                Some(state) => state,
+                None => break,
+            };
+
+            // the light is tinted on its way through a transparent object
+            if let Some(obj) = state.within {
+                let distance = state.t * ray.dir.mag();
+                throughput = throughput.blend(obj.material().attenuation(distance));
+            }
+
+            let material = state.obj.material();
+            if material.is_emissive() && !state.inside {
+                let weight = brdf_pdf.map_or(1.0, |pdf| {
+                    let distance = state.t * ray.dir.mag();
+                    let cos = state.eyev.normalize().dot(state.normal);
+                    let light_pdf = state.obj.surface_pdf(state.point) * distance * distance / cos;
+                    power_heuristic(1, pdf, world.emitter_sample_count(), light_pdf)
+                });
+
+                radiance = radiance + throughput.blend(material.emission) * weight;
+            }
+
+            if depth == self.max_depth {
+                break;
+            }
+
+            // transparent surfaces reflect or refract part of the light
+            // like a mirror, which light sampling cannot account for
+            let transparency = material.transparency();
+            if transparency > 0.0 && sampler.next_f32() < transparency {
+                let refracted = match state.refractv() {
+                    Some(dir) if sampler.next_f32() >= state.schlick() => Some(dir),
+                    _ => None,
+                };
+                ray = match refracted {
+                    Some(dir) => Ray::new(state.under_point, dir),
+                    None => Ray::new(state.over_point, state.reflectv()),
+                };
+                brdf_pdf = None;
+                if !self.survives(depth, &mut throughput, sampler) {
+                    break;
+                }
+                continue;
+            }
+
+            let vertex = Vertex::Surface(&state);
+            radiance = radiance + throughput.blend(direct_lighting(world, &vertex, sampler));
+
+            let sample = match material.sample(&state, state.eyev, sampler) {
+                Some(sample) if sample.pdf > 0.0 => sample,
+                _ => break,
+            };
+
+            let dir = sample.lightv;
+            throughput = throughput.blend(sample.value) * (dir.dot(state.normal) / sample.pdf);
+            brdf_pdf = Some(sample.pdf);
+            if !self.survives(depth, &mut throughput, sampler) {
+                break;
+            }
+
+            ray = Ray::new(state.over_point, dir);
+        }
+
+        radiance
+    }
+
+    /// Plays Russian roulette once the path is long enough. Returns `false`
+    /// if the path is terminated, and otherwise boosts the throughput to
+    /// make up for the terminated paths.
+    fn survives(&self, depth: u32, throughput: &mut Color, sampler: &mut Sampler) -> bool {
+        if depth + 1 < self.rr_depth {
+            return true;
+        }
+
+        let survive = throughput.max_component().clamp(0.05, 0.95);
+        if sampler.next_f32() >= survive {
+            return false;
+        }
+
+        *throughput = *throughput * (1.0 / survive);
+        true
+    }
+}
+
+/// Ambient occlusion: the fraction of the hemisphere above a hit which is
+/// not blocked by nearby objects.
+///
+/// As an integrator, it renders the unoccluded fraction at every camera hit
+/// in grey, e.g. for clay renders. With [`World::with_ambient_occlusion`],
+/// it darkens the ambient term of the Whitted integrator instead, which adds
+/// contact shadows to the otherwise flat ambient light.
+///
+/// [`World::with_ambient_occlusion`]: crate::world::World::with_ambient_occlusion
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientOcclusion {
+    /// Number of rays cast from every hit.
+    pub(crate) samples: u32,
+    /// Objects farther away than this do not occlude the hit.
+    pub(crate) max_distance: f32,
+}
+
+impl AmbientOcclusion {
+    /// Constructs a new `AmbientOcclusion` casting `samples` rays from every
+    /// hit, which are blocked by objects within `max_distance`.
+    pub fn new(samples: u32, max_distance: f32) -> Self {
+        Self {
+            samples,
+            max_distance,
+        }
+    }
+
+    /// Returns the unoccluded fraction at the first hit along the ray in
+    /// grey, or black if nothing is hit.
+    pub fn radiance(&self, world: &World, r: &Ray, sampler: &mut Sampler) -> Color {
+        let mut xs = world.intersect(r);
+        match xs.hit() {
+            Some(hit) => {
+                let unoccluded = self.unoccluded(world, &hit.prepare_computations(r), sampler);
+                Color::WHITE * unoccluded
+            }
+            None => Color::BLACK,
+        }
+    }
+
+    /// Returns the fraction of the rays cast from the hit, distributed by
+    /// the cosine to the normal, which travel `max_distance` without
+    /// hitting an object.
+    pub fn unoccluded(&self, world: &World, state: &HitState<'_>, sampler: &mut Sampler) -> f32 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+
+        let open = (0..self.samples)
+            .filter(|_| {
+                let (u, v) = sampler.next_2d();
+                let (x, y, z) = sampler::sample_cosine_hemisphere(u, v);
+                let r = Ray::new(state.over_point, around(state.normal, x, y, z));
+                let mut xs = world.intersect(&r);
+                !xs.hit().is_some_and(|h| h.t < self.max_distance)
+            })
+            .count();
+
+        open as f32 / self.samples as f32
+    }
+}
+
+/// A point at which the light along a path is scattered.
+pub(crate) enum Vertex<'a> {
+    /// A hit on the surface of an object.
+    Surface(&'a HitState<'a>),
+    /// A point inside a participating medium.
+    Medium {
+        /// Position of the point.
+        point: Point3,
+        /// Unit vector pointing back along the path.
+        eyev: Vec3,
+        /// The medium at the point.
+        medium: &'a Medium,
+    },
+}
+
+impl Vertex<'_> {
+    /// Returns the position of the vertex.
+    fn point(&self) -> Point3 {
+        match self {
+            Self::Surface(state) => state.point,
+            Self::Medium { point, .. } => *point,
+        }
+    }
+
+    /// Returns the origin of shadow rays leaving the vertex.
+    fn origin(&self) -> Point3 {
+        match self {
+            Self::Surface(state) => state.over_point,
+            Self::Medium { point, .. } => *point,
+        }
+    }
+
+    /// Returns the fraction of the light arriving along `lightv` which is
+    /// scattered back along the path, including the cosine factor on
+    /// surfaces, and the density of sampling `lightv`.
+    fn scatter(&self, lightv: Vec3) -> (Color, f32) {
+        match self {
+            Self::Surface(state) => {
+                let cos = lightv.dot(state.normal);
+                if cos <= 0.0 {
+                    return (Color::BLACK, 0.0);
+                }
+
+                let material = state.obj.material();
+                let f = material.eval(state, lightv, state.eyev);
+                (f * cos, material.pdf(state, lightv, state.eyev))
+            }
+            Self::Medium { eyev, medium, .. } => {
+                let phase = medium.phase(lightv, *eyev);
+                ([phase, phase, phase].into(), phase)
+            }
+        }
+    }
+
+    /// Returns the object the vertex lies on.
+    fn object(&self) -> Option<&Sphere> {
+        match self {
+            Self::Surface(state) => Some(state.obj),
+            Self::Medium { .. } => None,
+        }
+    }
+}
+
+/// Samples where the light along the ray is scattered by the media before
+/// `t_end` by delta tracking. Returns the point where the light is scattered
+/// along with the medium there. The throughput is updated in any case.
+///
+/// Tentative collisions are sampled with the same rate in all colour
+/// channels; whether they are real or null collisions is decided by the
+/// average extinction of the channels, and the throughput is weighted to
+/// account for the differences between the channels.
+fn sample_media(
+    world: &World,
+    r: &Ray,
+    t_end: f32,
+    throughput: &mut Color,
+    sampler: &mut Sampler,
+) -> Option<(f32, Medium)> {
+    let speed = r.dir.mag();
+    let mean = |c: Color| {
+        let [r, g, b] = c.into_inner().0;
+        (r + g + b) / 3.0
+    };
+
+    for (t0, t1, majorant) in world.media_intervals(r, t_end) {
+        if majorant <= 0.0 {
+            continue;
+        }
+
+        let mut t = t0;
+        loop {
+            t += -(1.0 - sampler.next_f32()).ln() / (majorant * speed);
+            if t >= t1 {
+                break;
+            }
+
+            let medium = match world.local_medium(r.pos(t)) {
+                Some(medium) => medium,
+                None => continue,
+            };
+            let sigma_t = medium.sigma_t();
+            let real = mean(sigma_t);
+            if sampler.next_f32() * majorant < real {
+                *throughput = throughput.blend(medium.sigma_s) * (1.0 / real);
+                return Some((t, medium));
+            }
+
+            let null = Color::WHITE * majorant - sigma_t;
+            *throughput = throughput.blend(null) * (1.0 / mean(null));
+        }
+    }
+
+    None
+}
+
+/// Returns the light scattered at the vertex back along the path which
+/// arrives directly from the light sources.
+///
+/// Samples of area lights and emissive objects are weighted against BRDF
+/// sampling, which finds the same lights when a path hits them.
+pub(crate) fn direct_lighting(world: &World, vertex: &Vertex<'_>, sampler: &mut Sampler) -> Color {
+    let (point, origin) = (vertex.point(), vertex.origin());
+    let mut color = Color::BLACK;
+
+    for light in &world.lights {
+        let area = match light {
+            Light::Area(area) => area,
+            _ => {
+                for sample in light.samples(point, sampler) {
+                    let (f, _) = vertex.scatter(sample.lightv);
+                    if f.max_component() <= 0.0 {
+                        continue;
+                    }
+
+                    let tr = world.transmittance(origin, &sample, sampler);
+                    color = color + f.blend(sample.intensity).blend(tr);
+                }
+                continue;
+            }
+        };
+
+        let count = area.sample_count();
+        for pos in area.sample_points(sampler) {
+            let sample = LightSample::towards(point, pos, area.radiance());
+            let light_pdf = area.pdf(sample.lightv, sample.distance);
+            let (f, brdf_pdf) = vertex.scatter(sample.lightv);
+            if f.max_component() <= 0.0 || light_pdf <= 0.0 {
+                continue;
+            }
+
+            let tr = world.transmittance(origin, &sample, sampler);
+            let weight = power_heuristic(count, light_pdf, 1, brdf_pdf);
+            color =
+                color + f.blend(sample.intensity).blend(tr) * (weight / (light_pdf * count as f32));
+        }
+    }
+
+    let count = world.emitter_sample_count();
+    for obj in world.emitters() {
+        if vertex.object().is_some_and(|o| std::ptr::eq(obj, o)) {
+            continue;
+        }
+
+        for (sample, light_pdf) in world.emitter_samples(obj, point, sampler) {
+            let (f, brdf_pdf) = vertex.scatter(sample.lightv);
+            if f.max_component() <= 0.0 {
+                continue;
+            }
+
+            let tr = world.transmittance_from(origin, &sample, Some(obj), sampler);
+            let weight = power_heuristic(count, light_pdf, 1, brdf_pdf);
+            color =
+                color + f.blend(sample.intensity).blend(tr) * (weight / (light_pdf * count as f32));
+        }
+    }
+
+    color
+}
+
+/// Returns the nearest area light hit by the ray before `t_max`.
+fn hit_light<'a>(world: &'a World, r: &Ray, t_max: f32) -> Option<(&'a AreaLight, f32)> {
+    world
+        .lights
+        .iter()
+        .filter_map(|light| match light {
+            Light::Area(area) => area.intersect(r).map(|t| (area, t)),
+            _ => None,
+        })
+        .filter(|&(_, t)| t < t_max)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Weight of a sample drawn `nf` times from the density `f_pdf` when
+/// the same integrand is also sampled `ng` times from `g_pdf`.
+fn power_heuristic(nf: u32, f_pdf: f32, ng: u32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+    if f.is_infinite() {
+        return 1.0;
+    }
+
+    f * f / (f * f + g * g)
+}
@@ -11,14 +11,31 @@
 
 #![warn(missing_docs, missing_debug_implementations, rust_2018_idioms)]
 
+pub mod anisotropic;
+pub mod aov;
+pub mod bsdf;
 pub mod camera;
+mod film;
+pub mod filter;
 pub mod hit_list;
+pub mod ies;
+pub mod integrator;
 pub mod lights;
 pub mod material;
 pub mod matrix;
+pub mod medium;
+pub mod oren_nayar;
+pub mod output;
+pub mod pbr;
+pub mod photon_map;
 pub mod ray;
+pub mod sampler;
+pub mod spectrum;
 pub mod sphere;
+pub mod stereo;
+mod tile;
 pub mod vec3;
+pub mod volume;
 pub mod world;
 
 use std::ops;
@@ -32,12 +49,34 @@ pub struct Color(image::Rgb<f32>);
 impl Color {
     /// The color Black.
     pub const BLACK: Color = Color(image::Rgb([0., 0., 0.]));
+    /// The color White.
+    pub const WHITE: Color = Color(image::Rgb([1., 1., 1.]));
 
     /// Consumes `self` and return the inner type.
     pub fn into_inner(self) -> image::Rgb<f32> {
         self.0
     }
 
+    /// Returns the colour of a blackbody radiator at the temperature
+    /// `kelvin`, scaled to a luminance of one.
+    ///
+    /// Useful to give lights the colour of real fixtures, e.g. `2700.0`
+    /// for a warm incandescent bulb or `5600.0` for daylight.
+    pub fn from_temperature(kelvin: f32) -> Self {
+        spectrum::blackbody(kelvin)
+    }
+
+    /// Returns the relative luminance of the colour, using the Rec. 709
+    /// primaries.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.0[0] + 0.7152 * self.0[1] + 0.0722 * self.0[2]
+    }
+
+    /// Returns the largest of the three components.
+    pub fn max_component(&self) -> f32 {
+        self.0[0].max(self.0[1]).max(self.0[2])
+    }
+
     /// Blends two `Color`s together.
     /// Performs component-wise multiplication.
     pub fn blend(&self, rhs: Self) -> Self {
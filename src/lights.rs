@@ -1,15 +1,114 @@
 //! This modules represents the different kinds of lights in a scene.
+//!
+//! Every light is sampled from the point being shaded. A light with no size
+//! gives a single sample, while area lights give several jittered samples
+//! spread over their surface which are averaged to produce soft shadows.
+//! Spotlights additionally restrict their light to a cone, and directional
+//! lights have no position at all.
+//!
+//! [`Light`] wraps every kind of light so that they can be mixed in a scene.
+//!
+//! By default the intensity of point and spot lights does not fall off with
+//! distance, unless they are given an [`Attenuation`]. The light of area
+//! lights falls off with the square of the distance like physical light.
+//! The brightness of lights can be specified as the emitted [`Power`], and
+//! their colour as a colour temperature, see [`Light::with_temperature`].
+//! Point and spot lights can also follow the measured distribution of a
+//! real fixture, see [`IesProfile`].
 
-use crate::{vec3::Point3, Color};
+use std::f32::consts::PI;
+
+use crate::{
+    ies::IesProfile,
+    ray::Ray,
+    sampler::{self, Sampler},
+    vec3::{Point3, Vec3},
+    Color,
+};
+
+/// Describes how the intensity of a light falls off with the distance.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Attenuation {
+    /// The intensity is the same at every distance.
+    #[default]
+    None,
+    /// The physically correct falloff, `1 / d^2`.
+    InverseSquare,
+    /// The classic `1 / (constant + linear * d + quadratic * d^2)` falloff.
+    Polynomial {
+        /// The constant term.
+        constant: f32,
+        /// The term proportional to the distance.
+        linear: f32,
+        /// The term proportional to the square of the distance.
+        quadratic: f32,
+    },
+}
+
+impl Attenuation {
+    /// Returns the fraction of the intensity remaining at `distance`.
+    pub fn factor(&self, distance: f32) -> f32 {
+        match *self {
+            Self::None => 1.0,
+            Self::InverseSquare => 1.0 / (distance * distance),
+            Self::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => 1.0 / (constant + linear * distance + quadratic * distance * distance),
+        }
+    }
+}
+
+/// The power emitted by a light, in radiometric or photometric units.
+///
+/// The colour of the light is kept and scaled to a luminance of one before
+/// the power is applied, so that lights of different colours with the same
+/// power appear equally bright.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    /// Radiant flux in watts.
+    Watts(f32),
+    /// Luminous flux in lumens.
+    Lumens(f32),
+}
+
+impl Power {
+    /// Luminous efficacy of radiation at 555nm, in lumens per watt.
+    pub const LUMINOUS_EFFICACY: f32 = 683.0;
+
+    /// Returns the power in watts.
+    pub fn watts(&self) -> f32 {
+        match *self {
+            Self::Watts(w) => w,
+            Self::Lumens(lm) => lm / Self::LUMINOUS_EFFICACY,
+        }
+    }
+
+    /// Returns the intensity which emits this power over `solid_angle`
+    /// steradians with the colour of `tint`.
+    fn intensity(&self, tint: Color, solid_angle: f32) -> Color {
+        let luminance = tint.luminance();
+        if luminance <= 0.0 {
+            return Color::BLACK;
+        }
+
+        tint * (self.watts() / (luminance * solid_angle))
+    }
+}
 
 /// Represents a point light - a light source with no size existing at
 /// a single point in space.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PointLight {
     /// Position of the light.
     pub(crate) pos: Point3,
     /// Describes the brightness and colour of the light.
     pub(crate) intensity: Color,
+    /// How the intensity falls off with the distance.
+    pub(crate) attenuation: Attenuation,
+    /// Distribution of the intensity over the directions.
+    pub(crate) profile: Option<IesProfile>,
 }
 
 impl PointLight {
@@ -18,6 +117,545 @@ impl PointLight {
         let pos = pos.into();
         let intensity = intensity.into();
 
-        Self { pos, intensity }
+        Self {
+            pos,
+            intensity,
+            attenuation: Attenuation::None,
+            profile: None,
+        }
+    }
+
+    /// Sets how the intensity falls off with the distance.
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Scales the intensity so that the light emits `power` in all directions,
+    /// keeping its colour.
+    pub fn with_power(mut self, power: Power) -> Self {
+        self.intensity = power.intensity(self.intensity, 4.0 * PI);
+        self
+    }
+
+    /// Modulates the intensity by the photometric profile of a fixture.
+    /// The intensity of the light is reached at the peak of the profile.
+    ///
+    /// The fixture points down, along `-y`, with the horizontal angle
+    /// zero along `+x`.
+    pub fn with_profile(mut self, profile: IesProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Returns the light arriving at `point`.
+    pub fn sample(&self, point: Point3) -> LightSample {
+        let mut sample =
+            LightSample::towards(point, self.pos, self.intensity).attenuate(self.attenuation);
+
+        if let Some(profile) = &self.profile {
+            let factor = profile.factor(
+                -sample.lightv,
+                Vec3::new(0., -1., 0.),
+                Vec3::new(1., 0., 0.),
+            );
+            sample.intensity = sample.intensity * factor;
+        }
+
+        sample
+    }
+}
+
+/// A point light which only shines inside a cone.
+///
+/// The light is at full strength inside the inner cone and fades out
+/// smoothly towards the edge of the outer cone.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    /// Position of the light.
+    pub(crate) pos: Point3,
+    /// Unit vector along the axis of the cone.
+    pub(crate) direction: Vec3,
+    /// Cosine of the angle between the axis and the edge of the inner cone.
+    pub(crate) cos_inner: f32,
+    /// Cosine of the angle between the axis and the edge of the outer cone.
+    pub(crate) cos_outer: f32,
+    /// Describes the brightness and colour of the light.
+    pub(crate) intensity: Color,
+    /// How the intensity falls off with the distance.
+    pub(crate) attenuation: Attenuation,
+    /// Distribution of the intensity over the directions.
+    pub(crate) profile: Option<IesProfile>,
+}
+
+impl SpotLight {
+    /// Constructs a new `SpotLight` at `pos` pointing along `direction`.
+    /// The angles are measured from the axis of the cone, in radians.
+    pub fn new(
+        pos: impl Into<Point3>,
+        direction: impl Into<Vec3>,
+        inner_angle: f32,
+        outer_angle: f32,
+        intensity: impl Into<Color>,
+    ) -> Self {
+        let outer_angle = outer_angle.max(inner_angle);
+
+        Self {
+            pos: pos.into(),
+            direction: direction.into().normalize(),
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.cos(),
+            intensity: intensity.into(),
+            attenuation: Attenuation::None,
+            profile: None,
+        }
+    }
+
+    /// Sets how the intensity falls off with the distance.
+    pub fn with_attenuation(mut self, attenuation: Attenuation) -> Self {
+        self.attenuation = attenuation;
+        self
+    }
+
+    /// Scales the intensity so that the light emits `power` inside its
+    /// cone, keeping its colour.
+    pub fn with_power(mut self, power: Power) -> Self {
+        // solid angle of the cone, with the falloff region counting half
+        let solid_angle = 2.0 * PI * (1.0 - 0.5 * (self.cos_inner + self.cos_outer));
+        self.intensity = power.intensity(self.intensity, solid_angle);
+        self
+    }
+
+    /// Modulates the intensity by the photometric profile of a fixture.
+    /// The intensity of the light is reached at the peak of the profile.
+    ///
+    /// The nadir of the fixture is the axis of the cone, which still
+    /// limits the light.
+    pub fn with_profile(mut self, profile: IesProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Returns the fraction of the intensity emitted along `dir`, a unit
+    /// vector pointing away from the light.
+    pub fn falloff(&self, dir: Vec3) -> f32 {
+        let cos_theta = dir.dot(self.direction);
+
+        if cos_theta >= self.cos_inner {
+            1.0
+        } else if cos_theta <= self.cos_outer {
+            0.0
+        } else {
+            // smoothstep between the outer and the inner cone
+            let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    /// Returns the light arriving at `point`.
+    pub fn sample(&self, point: Point3) -> LightSample {
+        let mut sample =
+            LightSample::towards(point, self.pos, self.intensity).attenuate(self.attenuation);
+        let dir = -sample.lightv;
+
+        let mut factor = self.falloff(dir);
+        if let Some(profile) = &self.profile {
+            let (reference, _) = self.direction.basis();
+            factor *= profile.factor(dir, self.direction, reference);
+        }
+        sample.intensity = sample.intensity * factor;
+
+        sample
+    }
+}
+
+/// A light infinitely far away, such as the sun, whose rays are all parallel.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Unit vector pointing in the direction the light travels.
+    pub(crate) direction: Vec3,
+    /// Describes the brightness and colour of the light.
+    pub(crate) intensity: Color,
+}
+
+impl DirectionalLight {
+    /// Constructs a new `DirectionalLight` shining along `direction`.
+    pub fn new(direction: impl Into<Vec3>, intensity: impl Into<Color>) -> Self {
+        Self {
+            direction: direction.into().normalize(),
+            intensity: intensity.into(),
+        }
+    }
+
+    /// Returns the light arriving at any point.
+    /// Shadow rays toward the light never stop.
+    pub fn sample(&self) -> LightSample {
+        LightSample {
+            lightv: -self.direction,
+            distance: f32::INFINITY,
+            intensity: self.intensity,
+        }
+    }
+}
+
+/// The shape of an [`AreaLight`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AreaShape {
+    /// A parallelogram spanned by two edges starting at a corner.
+    Rect {
+        /// One corner of the light.
+        corner: Point3,
+        /// The edge along which the `u` samples are spread.
+        uvec: Vec3,
+        /// The edge along which the `v` samples are spread.
+        vvec: Vec3,
+    },
+    /// A disk.
+    Disk {
+        /// Centre of the disk.
+        center: Point3,
+        /// Direction perpendicular to the disk.
+        normal: Vec3,
+        /// Radius of the disk.
+        radius: f32,
+    },
+}
+
+/// A light source with a surface. Every shaded point samples a grid of
+/// jittered points on the surface, so that the shadows get softer the
+/// bigger the light is.
+///
+/// Every point of the surface glows with the same radiance, see
+/// `radiance()`. The light arriving from it falls off with the square of
+/// the distance and with the angle to the surface, in the Whitted
+/// integrator and the path tracer alike.
+#[derive(Debug, Clone)]
+pub struct AreaLight {
+    /// The surface emitting the light.
+    pub(crate) shape: AreaShape,
+    /// Describes the brightness and colour of the whole light.
+    pub(crate) intensity: Color,
+    /// Number of cells along the `u` direction of the sampling grid.
+    pub(crate) usteps: u32,
+    /// Number of cells along the `v` direction of the sampling grid.
+    pub(crate) vsteps: u32,
+}
+
+impl AreaLight {
+    /// Constructs a rectangular `AreaLight` spanned by `uvec` and `vvec`
+    /// starting at `corner`.
+    pub fn new_rect(
+        corner: impl Into<Point3>,
+        uvec: impl Into<Vec3>,
+        vvec: impl Into<Vec3>,
+        intensity: impl Into<Color>,
+    ) -> Self {
+        let shape = AreaShape::Rect {
+            corner: corner.into(),
+            uvec: uvec.into(),
+            vvec: vvec.into(),
+        };
+
+        Self::new(shape, intensity.into())
+    }
+
+    /// Constructs a disk-shaped `AreaLight`.
+    pub fn new_disk(
+        center: impl Into<Point3>,
+        normal: impl Into<Vec3>,
+        radius: f32,
+        intensity: impl Into<Color>,
+    ) -> Self {
+        let shape = AreaShape::Disk {
+            center: center.into(),
+            normal: normal.into().normalize(),
+            radius,
+        };
+
+        Self::new(shape, intensity.into())
+    }
+
+    fn new(shape: AreaShape, intensity: Color) -> Self {
+        Self {
+            shape,
+            intensity,
+            usteps: 4,
+            vsteps: 4,
+        }
+    }
+
+    /// Sets the size of the grid of jittered samples.
+    /// Every shaded point takes `usteps * vsteps` samples.
+    pub fn with_samples(mut self, usteps: u32, vsteps: u32) -> Self {
+        self.usteps = usteps.max(1);
+        self.vsteps = vsteps.max(1);
+        self
+    }
+
+    /// Scales the intensity so that the light emits `power`, keeping its
+    /// colour. Every point of the light shines in all directions.
+    pub fn with_power(mut self, power: Power) -> Self {
+        self.intensity = power.intensity(self.intensity, 4.0 * PI);
+        self
+    }
+
+    /// Returns the point on the surface of the light for the sample `(u, v)`
+    /// in `[0, 1)^2`.
+    pub fn point_on_light(&self, u: f32, v: f32) -> Point3 {
+        match self.shape {
+            AreaShape::Rect { corner, uvec, vvec } => corner + uvec * u + vvec * v,
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let (t, b) = normal.basis();
+                let (x, y) = sampler::sample_disk(u, v);
+                center + (t * x + b * y) * radius
+            }
+        }
+    }
+
+    /// Returns a jittered sample from every cell of the sampling grid.
+    /// Each sample carries the light of its cell, the radiance divided by
+    /// the density of picking its direction, as in the path tracer.
+    pub fn samples(&self, point: Point3, sampler: &mut Sampler) -> Vec<LightSample> {
+        let count = self.sample_count() as f32;
+
+        self.sample_points(sampler)
+            .into_iter()
+            .map(|pos| {
+                let mut sample = LightSample::towards(point, pos, self.radiance());
+                let pdf = self.pdf(sample.lightv, sample.distance);
+                sample.intensity = if pdf > 0.0 {
+                    sample.intensity * (1.0 / (pdf * count))
+                } else {
+                    Color::BLACK
+                };
+                sample
+            })
+            .collect()
+    }
+
+    /// Returns a jittered point on the light from every cell of the
+    /// sampling grid.
+    pub(crate) fn sample_points(&self, sampler: &mut Sampler) -> Vec<Point3> {
+        let mut points = Vec::with_capacity((self.usteps * self.vsteps) as usize);
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let (ju, jv) = sampler.next_2d();
+                points.push(self.point_on_light(
+                    (u as f32 + ju) / self.usteps as f32,
+                    (v as f32 + jv) / self.vsteps as f32,
+                ));
+            }
+        }
+
+        points
+    }
+
+    /// Returns the number of samples taken for every shaded point.
+    pub fn sample_count(&self) -> u32 {
+        self.usteps * self.vsteps
+    }
+
+    /// Returns the area of the surface of the light.
+    pub fn area(&self) -> f32 {
+        match self.shape {
+            AreaShape::Rect { uvec, vvec, .. } => uvec.cross(vvec).mag(),
+            AreaShape::Disk { radius, .. } => PI * radius * radius,
+        }
+    }
+
+    /// Returns the unit normal of the surface of the light.
+    fn normal(&self) -> Vec3 {
+        match self.shape {
+            AreaShape::Rect { uvec, vvec, .. } => uvec.cross(vvec).normalize(),
+            AreaShape::Disk { normal, .. } => normal,
+        }
+    }
+
+    /// Returns the radiance emitted from every point of the light, on both
+    /// sides, when the light is treated as a glowing surface.
+    ///
+    /// The intensity is spread over the area of the light, so that a point
+    /// in front of a small light receives the same light as from a point
+    /// light of the same intensity with inverse square attenuation.
+    pub fn radiance(&self) -> Color {
+        self.intensity * (1.0 / self.area())
+    }
+
+    /// Returns the distance along the ray at which it hits the light.
+    pub fn intersect(&self, r: &Ray) -> Option<f32> {
+        let normal = self.normal();
+        let denom = r.dir.dot(normal);
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let origin = match self.shape {
+            AreaShape::Rect { corner, .. } => corner,
+            AreaShape::Disk { center, .. } => center,
+        };
+        let t = (origin - r.orig).dot(normal) / denom;
+        if t <= 0.0 {
+            return None;
+        }
+
+        let offset = r.pos(t) - origin;
+        let inside = match self.shape {
+            AreaShape::Rect { uvec, vvec, .. } => {
+                // coordinates of the hit along the edges of the parallelogram
+                let n = uvec.cross(vvec);
+                let u = offset.cross(vvec).dot(n) / n.mag_sq();
+                let v = uvec.cross(offset).dot(n) / n.mag_sq();
+                (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v)
+            }
+            AreaShape::Disk { radius, .. } => offset.mag_sq() <= radius * radius,
+        };
+
+        inside.then_some(t)
+    }
+
+    /// Returns the density, with respect to solid angle, of picking the
+    /// direction `lightv` towards the point of the light `distance` away
+    /// when sampling the surface of the light uniformly.
+    pub fn pdf(&self, lightv: Vec3, distance: f32) -> f32 {
+        let cos = lightv.dot(self.normal()).abs();
+        if cos <= 0.0 {
+            return 0.0;
+        }
+
+        distance * distance / (self.area() * cos)
+    }
+}
+
+/// Any of the lights which can be placed in the scene.
+#[derive(Debug, Clone)]
+pub enum Light {
+    /// A [`PointLight`].
+    Point(PointLight),
+    /// A [`SpotLight`].
+    Spot(SpotLight),
+    /// A [`DirectionalLight`].
+    Directional(DirectionalLight),
+    /// An [`AreaLight`].
+    Area(AreaLight),
+}
+
+impl Light {
+    /// Gives the light the colour of a blackbody at `kelvin`, keeping its
+    /// luminance. As the power set by `with_power()` is derived from the
+    /// luminance, it is kept too.
+    pub fn with_temperature(mut self, kelvin: f32) -> Self {
+        let intensity = match &mut self {
+            Self::Point(l) => &mut l.intensity,
+            Self::Spot(l) => &mut l.intensity,
+            Self::Directional(l) => &mut l.intensity,
+            Self::Area(l) => &mut l.intensity,
+        };
+        let tint = Color::from_temperature(kelvin);
+        *intensity = tint * (intensity.luminance() / tint.luminance());
+        self
+    }
+
+    /// Returns the total brightness and colour of the light.
+    pub fn intensity(&self) -> Color {
+        match self {
+            Self::Point(l) => l.intensity,
+            Self::Spot(l) => l.intensity,
+            Self::Directional(l) => l.intensity,
+            Self::Area(l) => l.intensity,
+        }
+    }
+
+    /// Returns the intensity of the light at `point` which feeds the
+    /// ambient term of the Phong model.
+    ///
+    /// It falls off with the distance like the light itself, but ignores
+    /// the cone and profile of a spotlight as well as any shadows: ambient
+    /// light stands for light bounced around the scene, which also reaches
+    /// the points the light does not shine on directly.
+    pub fn ambient(&self, point: Point3) -> Color {
+        let (pos, intensity, attenuation) = match self {
+            Self::Point(l) => (l.pos, l.intensity, l.attenuation),
+            Self::Spot(l) => (l.pos, l.intensity, l.attenuation),
+            Self::Directional(l) => return l.intensity,
+            Self::Area(l) => (
+                l.point_on_light(0.5, 0.5),
+                l.intensity,
+                Attenuation::InverseSquare,
+            ),
+        };
+
+        LightSample::towards(point, pos, intensity)
+            .attenuate(attenuation)
+            .intensity
+    }
+
+    /// Returns the samples of the light arriving at `point`.
+    pub fn samples(&self, point: Point3, sampler: &mut Sampler) -> Vec<LightSample> {
+        match self {
+            Self::Point(l) => vec![l.sample(point)],
+            Self::Spot(l) => vec![l.sample(point)],
+            Self::Directional(l) => vec![l.sample()],
+            Self::Area(l) => l.samples(point, sampler),
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(l: PointLight) -> Self {
+        Self::Point(l)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(l: SpotLight) -> Self {
+        Self::Spot(l)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(l: DirectionalLight) -> Self {
+        Self::Directional(l)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(l: AreaLight) -> Self {
+        Self::Area(l)
+    }
+}
+
+/// The light arriving at a point from a single point of a light source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Unit vector pointing from the shaded point toward the light.
+    pub lightv: Vec3,
+    /// Distance from the shaded point to the light.
+    /// Infinite for lights without a position.
+    pub distance: f32,
+    /// Brightness and colour of the light carried by this sample.
+    pub intensity: Color,
+}
+
+impl LightSample {
+    /// Constructs the sample of a light at `pos` arriving at `point`.
+    pub fn towards(point: Point3, pos: Point3, intensity: Color) -> Self {
+        let v = pos - point;
+        let distance = v.mag();
+
+        Self {
+            lightv: v / distance,
+            distance,
+            intensity,
+        }
+    }
+
+    /// Scales the intensity by the attenuation at the distance of the sample.
+    pub fn attenuate(mut self, attenuation: Attenuation) -> Self {
+        self.intensity = self.intensity * attenuation.factor(self.distance);
+        self
     }
 }
@@ -1,7 +1,7 @@
 use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4};
 
 use raytracer_rs::{
-    camera::Camera, lights::PointLight, material::Material, matrix::Mat4, sphere::Sphere,
+    camera::Camera, lights::PointLight, material::Material, matrix::Mat4, output, sphere::Sphere,
     world::World,
 };
 
@@ -63,14 +63,19 @@ fn main() {
         vec![light],
     );
 
-    let camera = Camera::new(400, 200, FRAC_PI_3).with_transform(Mat4::view_transform(
-        (0., 1.5, -5.).into(),
-        (0., 1., 0.).into(),
-        (0., 1., 0.).into(),
-    ));
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let camera = Camera::new(400, 200, FRAC_PI_3)
+        .with_transform(Mat4::view_transform(
+            (0., 1.5, -5.).into(),
+            (0., 1., 0.).into(),
+            (0., 1., 0.).into(),
+        ))
+        .with_threads(threads);
 
-    let canvas = camera.render(&world);
-    let canvas = image::DynamicImage::ImageRgb32F(canvas).to_rgb8();
-
-    canvas.save(&file).unwrap();
+    // OpenEXR files get the output variables along with the colour
+    if file.to_ascii_lowercase().ends_with(".exr") {
+        output::save(&camera.render_aovs(&world), &file).unwrap();
+    } else {
+        output::save_image(&camera.render(&world), &file).unwrap();
+    }
 }
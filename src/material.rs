@@ -1,14 +1,29 @@
 //! This module describes the material properties of the objects in
 //! the scene. We use the Phong reflection model here.
+//!
+//! For the path tracer, the same parameters are interpreted as an
+//! energy-conserving BRDF: a Lambertian diffuse lobe plus a normalized
+//! Phong specular lobe. Alternatively, a material may use the physically
+//! based [`PbrMaterial`] or any other [`Bsdf`], which then replaces the
+//! Phong model.
+//!
+//! Transparent materials let part of the light through, bent at the surface
+//! and tinted by absorption inside the object.
+
+use std::{f32::consts::PI, sync::Arc};
 
 use crate::{
-    lights::PointLight,
-    vec3::{Point3, Vec3},
+    bsdf::{self, Bsdf, BsdfSample},
+    hit_list::HitState,
+    lights::{LightSample, PointLight},
+    pbr::PbrMaterial,
+    sampler::{self, Sampler},
+    vec3::Vec3,
     Color,
 };
 
 /// Describes the properties of the material.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Material {
     /// The color of the object.
     pub(crate) color: Color,
@@ -22,6 +37,18 @@ pub struct Material {
     pub(crate) specular: f32,
     /// Controls the specular highlight.
     pub(crate) shininess: f32,
+    /// Radiance emitted by the outside of the surface. Objects with a
+    /// non-black emission act as light sources.
+    pub(crate) emission: Color,
+    /// Replaces the Phong model when set.
+    pub(crate) bsdf: Option<Arc<dyn Bsdf>>,
+    /// Fraction of the light passing through the surface.
+    pub(crate) transparency: f32,
+    /// Bends the light passing through the surface.
+    pub(crate) refractive_index: f32,
+    /// Absorption coefficient per unit distance of the inside of a
+    /// transparent object.
+    pub(crate) absorption: Color,
 }
 
 impl Material {
@@ -33,36 +60,150 @@ impl Material {
             diffuse,
             specular,
             shininess,
+            emission: Color::BLACK,
+            bsdf: None,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            absorption: Color::BLACK,
         }
     }
 
-    /// Responsible for shading the point based on the material.
-    pub fn lighting(&self, light: &PointLight, pos: Point3, eyev: Vec3, normal: Vec3) -> Color {
-        let effective_color = self.color.blend(light.intensity);
-        let lightv = (light.pos - pos).normalize();
+    /// Responsible for shading the hit based on the material, lit by a
+    /// single point light which is not in shadow. The ambient term falls
+    /// off with the distance like the light, see
+    /// [`Light::ambient`](crate::lights::Light::ambient).
+    pub fn lighting(&self, light: &PointLight, state: &HitState<'_>) -> Color {
+        let sample = light.sample(state.point);
+        let ambient = light.intensity * light.attenuation.factor(sample.distance);
+        self.lighting_samples(ambient, &[sample], state)
+    }
+
+    /// Shades a hit lit by several samples of one light, e.g. the points
+    /// of an area light which are not in shadow.
+    ///
+    /// The ambient term depends only on the total `intensity` of the light,
+    /// while every sample adds its share of the light reflected by the BSDF.
+    pub fn lighting_samples(
+        &self,
+        intensity: Color,
+        samples: &[LightSample],
+        state: &HitState<'_>,
+    ) -> Color {
+        samples
+            .iter()
+            .map(|s| self.shade(state, s, state.eyev))
+            .fold(self.ambient_term(intensity), |acc, c| acc + c)
+    }
+
+    /// Returns the constant background lighting for a light of the given
+    /// intensity.
+    fn ambient_term(&self, intensity: Color) -> Color {
+        self.color.blend(intensity) * self.ambient
+    }
+
+    /// Returns the diffuse and specular reflection of a single light sample.
+    fn phong(&self, sample: &LightSample, eyev: Vec3, normal: Vec3) -> Color {
+        let effective_color = self.color.blend(sample.intensity);
+        let lightv = sample.lightv;
 
-        let ambient = effective_color * self.ambient;
         let light_dot_normal = lightv.dot(normal);
 
-        let (diffuse, specular) = if light_dot_normal < 0.0 {
-            (Color::BLACK, Color::BLACK)
+        if light_dot_normal < 0.0 {
+            return Color::BLACK;
+        }
+
+        let diffuse = effective_color * self.diffuse * light_dot_normal;
+
+        let reflectv = (-lightv).reflect(normal);
+        let reflect_dot_eye = reflectv.dot(eyev);
+
+        let specular = if reflect_dot_eye <= 0.0 {
+            Color::BLACK
         } else {
-            let diffuse = effective_color * self.diffuse * light_dot_normal;
+            let factor = reflect_dot_eye.powf(self.shininess);
+            sample.intensity * self.specular * factor
+        };
+
+        diffuse + specular
+    }
+
+    /// Evaluates the Phong model as a BRDF.
+    ///
+    /// Unlike `lighting()`, the result is physically based: the diffuse
+    /// lobe is divided by `π`, the specular lobe is normalized and the
+    /// lobes are scaled down if they would reflect more light than arrives.
+    fn phong_brdf(&self, lightv: Vec3, eyev: Vec3, normal: Vec3) -> Color {
+        if lightv.dot(normal) <= 0.0 || eyev.dot(normal) <= 0.0 {
+            return Color::BLACK;
+        }
+
+        let (kd, ks) = self.lobe_weights();
+        let diffuse = self.color * (kd / PI);
+
+        let reflect_dot_eye = (-lightv).reflect(normal).dot(eyev);
+        if reflect_dot_eye <= 0.0 {
+            return diffuse;
+        }
+
+        let n = self.shininess;
+        let specular = ks * (n + 2.0) / (2.0 * PI) * reflect_dot_eye.powf(n);
 
-            let reflectv = (-lightv).reflect(normal);
-            let reflect_dot_eye = reflectv.dot(eyev);
+        diffuse + [specular, specular, specular].into()
+    }
 
-            let specular = if reflect_dot_eye <= 0.0 {
-                Color::BLACK
-            } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                light.intensity * self.specular * factor
-            };
+    /// Samples a direction from one of the lobes of the Phong BRDF.
+    fn sample_phong(&self, eyev: Vec3, normal: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        let (kd, ks) = self.lobe_weights();
+        if kd + ks <= 0.0 {
+            return None;
+        }
 
-            (diffuse, specular)
+        // choose one of the lobes and sample it
+        let (u, v) = sampler.next_2d();
+        let choice = sampler.next_f32() * (kd + ks);
+        let dir = if choice < kd {
+            let (x, y, z) = sampler::sample_cosine_hemisphere(u, v);
+            around(normal, x, y, z)
+        } else {
+            let cos = u.powf(1.0 / (self.shininess + 1.0));
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+            let phi = 2.0 * PI * v;
+            around(
+                (-eyev).reflect(normal),
+                sin * phi.cos(),
+                sin * phi.sin(),
+                cos,
+            )
         };
 
-        ambient + diffuse + specular
+        (dir.dot(normal) > 0.0).then_some(dir)
+    }
+
+    /// Returns the density with which `sample_phong()` picks `lightv`.
+    fn phong_pdf(&self, lightv: Vec3, eyev: Vec3, normal: Vec3) -> f32 {
+        let (kd, ks) = self.lobe_weights();
+        let cos = lightv.dot(normal);
+        if kd + ks <= 0.0 || cos <= 0.0 {
+            return 0.0;
+        }
+
+        let diffuse = cos / PI;
+        let reflect_dot_light = (-eyev).reflect(normal).dot(lightv).max(0.0);
+        let n = self.shininess;
+        let specular = (n + 1.0) / (2.0 * PI) * reflect_dot_light.powf(n);
+
+        (kd * diffuse + ks * specular) / (kd + ks)
+    }
+
+    /// Returns the weights of the diffuse and specular lobes of the BRDF,
+    /// scaled so that they add up to at most one.
+    fn lobe_weights(&self) -> (f32, f32) {
+        let total = self.diffuse + self.specular;
+        if total > 1.0 {
+            (self.diffuse / total, self.specular / total)
+        } else {
+            (self.diffuse, self.specular)
+        }
     }
 
     /// Sets the color.
@@ -90,6 +231,73 @@ impl Material {
         self.shininess = shininess;
         self
     }
+
+    /// Makes the surface glow with the given colour, scaled by `strength`.
+    pub fn with_emission(mut self, color: Color, strength: f32) -> Self {
+        self.emission = color * strength;
+        self
+    }
+
+    /// Lets the given fraction of the light pass through the surface, bent
+    /// according to the `refractive_index`, e.g. `1.5` for glass.
+    pub fn with_transparency(mut self, transparency: f32, refractive_index: f32) -> Self {
+        self.transparency = transparency;
+        self.refractive_index = refractive_index;
+        self
+    }
+
+    /// Tints the light passing through a transparent object, following the
+    /// Beer-Lambert law: after travelling `distance` inside the object,
+    /// white light has turned into `color`. Thicker parts of the object
+    /// therefore look darker.
+    pub fn with_absorption(mut self, color: Color, distance: f32) -> Self {
+        let [r, g, b] = color.into_inner().0;
+        let sigma = |c: f32| -c.clamp(1e-6, 1.0).ln() / distance;
+        self.absorption = [sigma(r), sigma(g), sigma(b)].into();
+        self
+    }
+
+    /// Replaces the Phong model with a custom shading model. The colour of
+    /// the material still tints the ambient term.
+    pub fn with_bsdf(mut self, bsdf: impl Bsdf + 'static) -> Self {
+        self.bsdf = Some(Arc::new(bsdf));
+        self
+    }
+
+    /// Describes the surface with a physically based material instead of
+    /// the Phong model. The colour of the material is set to the base
+    /// colour, which tints the ambient term.
+    pub fn with_pbr(mut self, pbr: PbrMaterial) -> Self {
+        self.color = pbr.base_color;
+        self.with_bsdf(pbr)
+    }
+
+    /// Returns the radiance emitted by the surface.
+    pub fn emission(&self) -> Color {
+        self.emission
+    }
+
+    /// Returns `true` if the surface emits light.
+    pub fn is_emissive(&self) -> bool {
+        self.emission.max_component() > 0.0
+    }
+
+    /// Returns the fraction of the light passing through the surface.
+    pub fn transparency(&self) -> f32 {
+        self.transparency
+    }
+
+    /// Returns the fraction of the light which is not absorbed after
+    /// travelling `distance` inside the object.
+    pub fn attenuation(&self, distance: f32) -> Color {
+        let [r, g, b] = self.absorption.into_inner().0;
+        [
+            (-r * distance).exp(),
+            (-g * distance).exp(),
+            (-b * distance).exp(),
+        ]
+        .into()
+    }
 }
 
 impl Default for Material {
@@ -100,6 +308,90 @@ impl Default for Material {
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            emission: Color::BLACK,
+            bsdf: None,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            absorption: Color::BLACK,
+        }
+    }
+}
+
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        let same_bsdf = match (&self.bsdf, &other.bsdf) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b) || a.eq_dyn(b.as_ref()),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        same_bsdf
+            && self.color == other.color
+            && self.ambient == other.ambient
+            && self.diffuse == other.diffuse
+            && self.specular == other.specular
+            && self.shininess == other.shininess
+            && self.emission == other.emission
+            && self.transparency == other.transparency
+            && self.refractive_index == other.refractive_index
+            && self.absorption == other.absorption
+    }
+}
+
+impl Bsdf for Material {
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> Color {
+        match &self.bsdf {
+            Some(bsdf) => bsdf.eval(state, lightv, eyev),
+            None => self.phong_brdf(lightv, eyev, state.normal),
+        }
+    }
+
+    fn sample(
+        &self,
+        state: &HitState<'_>,
+        eyev: Vec3,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        if let Some(bsdf) = &self.bsdf {
+            return bsdf.sample(state, eyev, sampler);
+        }
+
+        let lightv = self.sample_phong(eyev, state.normal, sampler)?;
+        Some(BsdfSample {
+            lightv,
+            value: self.phong_brdf(lightv, eyev, state.normal),
+            pdf: self.phong_pdf(lightv, eyev, state.normal),
+        })
+    }
+
+    fn pdf(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> f32 {
+        match &self.bsdf {
+            Some(bsdf) => bsdf.pdf(state, lightv, eyev),
+            None => self.phong_pdf(lightv, eyev, state.normal),
         }
     }
+
+    fn shade(&self, state: &HitState<'_>, sample: &LightSample, eyev: Vec3) -> Color {
+        match &self.bsdf {
+            Some(bsdf) => bsdf.shade(state, sample, eyev),
+            None => self.phong(sample, eyev, state.normal),
+        }
+    }
+
+    fn albedo(&self, state: &HitState<'_>) -> Color {
+        match &self.bsdf {
+            Some(bsdf) => bsdf.albedo(state),
+            None => self.color,
+        }
+    }
+
+    fn eq_dyn(&self, other: &dyn Bsdf) -> bool {
+        bsdf::eq_as(self, other)
+    }
+}
+
+/// Returns the direction with coordinates `(x, y, z)` in a frame whose
+/// `z` axis is `axis`.
+pub(crate) fn around(axis: Vec3, x: f32, y: f32, z: f32) -> Vec3 {
+    let (t, b) = axis.basis();
+    t * x + b * y + axis * z
 }
@@ -0,0 +1,127 @@
+//! Participating media such as fog, smoke or murky water.
+//!
+//! A medium absorbs and scatters the light travelling through it. Both
+//! effects are described by coefficients, which give the probability per
+//! unit distance of the light being absorbed or scattered. Light which is
+//! scattered continues in a direction given by the Henyey-Greenstein phase
+//! function.
+//!
+//! Media either fill the whole scene as fog, see
+//! [`World::with_fog`](crate::world::World::with_fog), or the inside of a
+//! shape, see [`Sphere::with_medium`](crate::sphere::Sphere::with_medium).
+//! Media whose density varies are described by a
+//! [`Volume`](crate::volume::Volume).
+
+use std::f32::consts::PI;
+
+use crate::{material::around, sampler::Sampler, vec3::Vec3, Color};
+
+/// A homogeneous participating medium.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Medium {
+    /// Absorption coefficient per unit distance.
+    pub(crate) sigma_a: Color,
+    /// Scattering coefficient per unit distance.
+    pub(crate) sigma_s: Color,
+    /// Asymmetry of the phase function in `(-1, 1)`. Positive values scatter
+    /// the light forwards, negative values backwards.
+    pub(crate) g: f32,
+}
+
+impl Medium {
+    /// Constructs a new `Medium` from its absorption and scattering
+    /// coefficients.
+    pub fn new(sigma_a: Color, sigma_s: Color, g: f32) -> Self {
+        Self {
+            sigma_a,
+            sigma_s,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    /// Constructs a grey, non-absorbing fog in which light travels
+    /// `1 / density` on average before being scattered.
+    pub fn new_fog(density: f32) -> Self {
+        Self::new(Color::BLACK, [density, density, density].into(), 0.0)
+    }
+
+    /// Returns the extinction coefficient, the sum of the absorption and
+    /// scattering coefficients.
+    pub fn sigma_t(&self) -> Color {
+        self.sigma_a + self.sigma_s
+    }
+
+    /// Returns the fraction of light passing through `distance` of the
+    /// medium unaffected.
+    pub fn transmittance(&self, distance: f32) -> Color {
+        let [r, g, b] = self.sigma_t().into_inner().0;
+        let tr = |sigma: f32| {
+            if sigma <= 0.0 {
+                1.0
+            } else {
+                (-sigma * distance).exp()
+            }
+        };
+
+        [tr(r), tr(g), tr(b)].into()
+    }
+
+    /// Returns the density of light arriving along `lightv` being scattered
+    /// along `eyev`. Both vectors point away from the scattering point.
+    pub fn phase(&self, lightv: Vec3, eyev: Vec3) -> f32 {
+        henyey_greenstein(-lightv.dot(eyev), self.g)
+    }
+
+    /// Samples a direction for the light arriving at a point of the medium
+    /// which is scattered along `eyev`. The density of the direction is
+    /// given by `phase()`.
+    pub fn sample_phase(&self, eyev: Vec3, sampler: &mut Sampler) -> Vec3 {
+        let (u, v) = sampler.next_2d();
+        let g = self.g;
+
+        // cosine of the angle between the propagation directions
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 + g - 2.0 * g * u);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+
+        // the light travels along `-lightv` and continues along `eyev`
+        -around(eyev, sin * phi.cos(), sin * phi.sin(), cos)
+    }
+
+    /// Combines two media filling the same space.
+    pub fn combine(&self, other: &Medium) -> Medium {
+        let (w1, w2) = (self.sigma_s.luminance(), other.sigma_s.luminance());
+        let g = if w1 + w2 > 0.0 {
+            (self.g * w1 + other.g * w2) / (w1 + w2)
+        } else {
+            0.0
+        };
+
+        Medium::new(
+            self.sigma_a + other.sigma_a,
+            self.sigma_s + other.sigma_s,
+            g,
+        )
+    }
+
+    /// Returns the medium with its coefficients scaled by `density`.
+    pub(crate) fn scaled(&self, density: f32) -> Medium {
+        Medium {
+            sigma_a: self.sigma_a * density,
+            sigma_s: self.sigma_s * density,
+            g: self.g,
+        }
+    }
+}
+
+/// The Henyey-Greenstein phase function for the cosine of the angle
+/// between the incoming and outgoing propagation directions.
+fn henyey_greenstein(cos: f32, g: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos;
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
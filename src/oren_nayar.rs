@@ -0,0 +1,83 @@
+//! The Oren-Nayar model of rough diffuse surfaces.
+//!
+//! Rough surfaces such as clay, plaster or cloth are made of tiny facets
+//! which are Lambertian on their own. Seen together, they reflect more light
+//! back towards the light source than a Lambertian surface and look flatter.
+//! We use the qualitative model from Oren and Nayar's paper.
+
+use std::f32::consts::PI;
+
+use crate::{
+    bsdf::{self, Bsdf},
+    hit_list::HitState,
+    vec3::Vec3,
+    Color,
+};
+
+/// A rough diffuse material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrenNayar {
+    /// Colour of the surface.
+    pub(crate) albedo: Color,
+    /// Coefficient `A` of the model, derived from the roughness.
+    a: f32,
+    /// Coefficient `B` of the model, derived from the roughness.
+    b: f32,
+}
+
+impl OrenNayar {
+    /// Constructs a new `OrenNayar` material. `sigma` is the standard
+    /// deviation of the slopes of the facets in radians. With `sigma = 0`,
+    /// the surface is Lambertian.
+    pub fn new(albedo: Color, sigma: f32) -> Self {
+        let s2 = sigma * sigma;
+
+        Self {
+            albedo,
+            a: 1.0 - 0.5 * s2 / (s2 + 0.33),
+            b: 0.45 * s2 / (s2 + 0.09),
+        }
+    }
+}
+
+impl Bsdf for OrenNayar {
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> Color {
+        let normal = state.normal;
+        let cos_i = lightv.dot(normal);
+        let cos_o = eyev.dot(normal);
+        if cos_i <= 0.0 || cos_o <= 0.0 {
+            return Color::BLACK;
+        }
+
+        // cosine of the azimuthal angle between the two directions,
+        // computed from their projections onto the tangent plane
+        let proj_i = lightv - normal * cos_i;
+        let proj_o = eyev - normal * cos_o;
+        let len = (proj_i.mag_sq() * proj_o.mag_sq()).sqrt();
+        let cos_phi = if len > 1e-8 {
+            (proj_i.dot(proj_o) / len).max(0.0)
+        } else {
+            0.0
+        };
+
+        // sin(alpha) tan(beta), where alpha is the larger of the two polar
+        // angles and beta the smaller one
+        let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+        let sin_alpha_tan_beta = if cos_i > cos_o {
+            sin_o * sin_i / cos_i
+        } else {
+            sin_i * sin_o / cos_o
+        };
+
+        self.albedo * ((self.a + self.b * cos_phi * sin_alpha_tan_beta) / PI)
+    }
+
+    fn albedo(&self, _state: &HitState<'_>) -> Color {
+        self.albedo
+    }
+
+    fn eq_dyn(&self, other: &dyn Bsdf) -> bool {
+        bsdf::eq_as(self, other)
+    }
+}
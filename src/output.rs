@@ -0,0 +1,292 @@
+//! Writing rendered images to files.
+//!
+//! The renderer computes linear floating point colours. To keep their full
+//! range for compositing, images can be written in three high dynamic range
+//! formats, picked by the extension of the file name:
+//!
+//! - `.exr`: OpenEXR with uncompressed 32-bit float channels. Along with the
+//!   colour, the file holds the arbitrary output variables of a
+//!   [`RenderResult`] as layers.
+//! - `.hdr`: Radiance RGBE.
+//! - `.pfm`: Portable float map.
+//!
+//! Any other extension is written by the `image` crate with 8 bits per
+//! channel, clamping the colours to `[0, 1]`.
+
+use std::{
+    error, fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use image::{codecs::hdr::HdrEncoder, ImageError, Rgb32FImage};
+
+use crate::aov::RenderResult;
+
+/// Errors which can occur while writing an image.
+#[derive(Debug)]
+pub enum OutputError {
+    /// The file could not be written.
+    Io(io::Error),
+    /// The `image` crate failed to encode the image.
+    Image(ImageError),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to write image: {}", e),
+            Self::Image(e) => write!(f, "failed to encode image: {}", e),
+        }
+    }
+}
+
+impl error::Error for OutputError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Image(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(e: ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+/// A named channel of an OpenEXR image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    /// Name of the channel. Channels of a layer are named `layer.channel`,
+    /// those of the default layer just `channel`.
+    pub name: String,
+    /// Values of the pixels, row by row from the top.
+    pub values: Vec<f32>,
+}
+
+impl Channel {
+    /// Constructs a new `Channel`.
+    pub fn new(name: impl Into<String>, values: Vec<f32>) -> Self {
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+}
+
+/// Writes the render to `path`, in the format given by its extension.
+/// OpenEXR files get all the output variables, the other formats only the
+/// colour.
+pub fn save(result: &RenderResult, path: impl AsRef<Path>) -> Result<(), OutputError> {
+    let path = path.as_ref();
+    if extension(path).as_deref() != Some("exr") {
+        return save_image(&result.color, path);
+    }
+
+    let (width, height) = result.color.dimensions();
+    let mut file = BufWriter::new(File::create(path)?);
+    write_exr(&mut file, width, height, &channels(result))?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes the image to `path`, in the format given by its extension.
+pub fn save_image(image: &Rgb32FImage, path: impl AsRef<Path>) -> Result<(), OutputError> {
+    let path = path.as_ref();
+    let (width, height) = image.dimensions();
+
+    let ext = extension(path);
+    if !matches!(ext.as_deref(), Some("exr" | "hdr" | "pfm")) {
+        let image = image::DynamicImage::ImageRgb32F(image.clone()).to_rgb8();
+        return Ok(image.save(path)?);
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    match ext.as_deref() {
+        Some("exr") => {
+            let channels = layer("", ["R", "G", "B"], image.pixels().map(|p| p.0));
+            write_exr(&mut file, width, height, &channels)?;
+        }
+        Some("hdr") => {
+            let pixels: Vec<_> = image.pixels().copied().collect();
+            HdrEncoder::new(&mut file).encode(&pixels, width as usize, height as usize)?;
+        }
+        _ => write_pfm(&mut file, image)?,
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes the image as a colour portable float map.
+pub fn write_pfm(w: &mut impl Write, image: &Rgb32FImage) -> io::Result<()> {
+    let (width, height) = image.dimensions();
+    // a negative scale marks little-endian data
+    write!(w, "PF\n{} {}\n-1.0\n", width, height)?;
+
+    // the rows are stored from the bottom up
+    for row in image.rows().rev() {
+        for pixel in row {
+            for c in pixel.0 {
+                w.write_all(&c.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the channels as an uncompressed single-part scanline OpenEXR
+/// image of `width x height` pixels. The values are stored as 32-bit floats.
+///
+/// # Panics
+///
+/// Panics if a channel does not have a value for every pixel.
+pub fn write_exr(
+    w: &mut impl Write,
+    width: u32,
+    height: u32,
+    channels: &[Channel],
+) -> io::Result<()> {
+    const FLOAT: i32 = 2;
+
+    let pixels = width as usize * height as usize;
+    assert!(
+        channels.iter().all(|c| c.values.len() == pixels),
+        "every channel needs a value for every pixel"
+    );
+
+    // readers expect the channels in alphabetical order
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut chlist = Vec::new();
+    for c in &channels {
+        chlist.extend_from_slice(c.name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&FLOAT.to_le_bytes());
+        // linear flag and reserved bytes, then the sampling in x and y
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let mut header = Vec::new();
+    // magic number and version 2 without any flags
+    header.extend_from_slice(&20000630i32.to_le_bytes());
+    header.extend_from_slice(&2i32.to_le_bytes());
+
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        for s in [name, kind] {
+            header.extend_from_slice(s.as_bytes());
+            header.push(0);
+        }
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    };
+    attribute("channels", "chlist", &chlist);
+    attribute("compression", "compression", &[0]);
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+    w.write_all(&header)?;
+
+    // every scanline is a block of its own, listed in the offset table
+    let line_size = 4 * width as usize * channels.len();
+    let block_size = 8 + line_size;
+    let table_end = header.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        w.write_all(&((table_end + y * block_size) as u64).to_le_bytes())?;
+    }
+
+    let width = width as usize;
+    for y in 0..height as usize {
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&(line_size as i32).to_le_bytes())?;
+        for c in &channels {
+            for v in &c.values[y * width..(y + 1) * width] {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the channels of the render: the colour in the default layer
+/// and every output variable in a layer of its own.
+fn channels(result: &RenderResult) -> Vec<Channel> {
+    let mut channels = Vec::new();
+    channels.extend(layer(
+        "",
+        ["R", "G", "B"],
+        result.color.pixels().map(|p| p.0),
+    ));
+    channels.extend(layer("", ["Z"], result.depth.pixels().map(|p| p.0)));
+    channels.extend(layer(
+        "normal",
+        ["X", "Y", "Z"],
+        result.normal.pixels().map(|p| p.0),
+    ));
+    channels.extend(layer(
+        "position",
+        ["X", "Y", "Z"],
+        result.position.pixels().map(|p| p.0),
+    ));
+    channels.extend(layer(
+        "albedo",
+        ["R", "G", "B"],
+        result.albedo.pixels().map(|p| p.0),
+    ));
+    channels.extend(layer("object", ["id"], result.object.pixels().map(|p| p.0)));
+    channels.extend(layer("uv", ["U", "V"], result.uv.pixels().map(|p| p.0)));
+    channels
+}
+
+/// Splits the pixels into the channels of a layer. An empty layer name
+/// stands for the default layer.
+fn layer<const N: usize>(
+    name: &str,
+    channels: [&str; N],
+    pixels: impl Iterator<Item = [f32; N]>,
+) -> Vec<Channel> {
+    let pixels: Vec<[f32; N]> = pixels.collect();
+    channels
+        .iter()
+        .enumerate()
+        .map(|(i, channel)| {
+            let channel = if name.is_empty() {
+                channel.to_string()
+            } else {
+                format!("{}.{}", name, channel)
+            };
+            Channel::new(channel, pixels.iter().map(|p| p[i]).collect())
+        })
+        .collect()
+}
+
+/// Returns the lowercase extension of the path.
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+}
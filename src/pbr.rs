@@ -0,0 +1,258 @@
+//! A physically based material following the metallic/roughness workflow.
+//!
+//! The surface is a mix of a Lambertian diffuse base and a specular layer of
+//! microfacets with the GGX (Trowbridge-Reitz) distribution, Smith masking
+//! and Schlick's approximation of the Fresnel term. An optional clearcoat
+//! adds a second, colourless specular layer on top.
+
+use std::f32::consts::PI;
+
+use crate::{
+    bsdf::{self, Bsdf, BsdfSample},
+    hit_list::HitState,
+    material::around,
+    sampler::{self, Sampler},
+    vec3::Vec3,
+    Color,
+};
+
+/// Smallest roughness used for the microfacet distribution. Perfectly
+/// smooth surfaces would make the distribution a delta function.
+const MIN_ALPHA: f32 = 1e-3;
+
+/// Describes a material in the metallic/roughness workflow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    /// Albedo of dielectrics, or the reflectance of metals.
+    pub(crate) base_color: Color,
+    /// Blends between a dielectric (`0`) and a metal (`1`).
+    pub(crate) metallic: f32,
+    /// Perceptual roughness in `[0, 1]`.
+    pub(crate) roughness: f32,
+    /// Specular reflectance of dielectrics. The default `0.5` corresponds
+    /// to a reflectance of 4% at normal incidence.
+    pub(crate) specular: f32,
+    /// Strength of the clearcoat layer.
+    pub(crate) clearcoat: f32,
+    /// Perceptual roughness of the clearcoat layer.
+    pub(crate) clearcoat_roughness: f32,
+}
+
+impl PbrMaterial {
+    /// Constructs a new `PbrMaterial`.
+    pub fn new(base_color: Color, metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            metallic,
+            roughness,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the specular reflectance of dielectrics.
+    pub fn with_specular(mut self, specular: f32) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    /// Adds a clearcoat layer with the given strength and roughness.
+    pub fn with_clearcoat(mut self, clearcoat: f32, roughness: f32) -> Self {
+        self.clearcoat = clearcoat;
+        self.clearcoat_roughness = roughness;
+        self
+    }
+
+    /// Returns the base colour.
+    pub fn base_color(&self) -> Color {
+        self.base_color
+    }
+
+    /// Reflectance at normal incidence.
+    fn f0(&self) -> Color {
+        let dielectric = 0.08 * self.specular;
+        let dielectric: Color = [dielectric, dielectric, dielectric].into();
+
+        dielectric * (1.0 - self.metallic) + self.base_color * self.metallic
+    }
+
+    /// Width of the GGX distribution, remapped from the perceptual roughness.
+    fn alpha(&self) -> f32 {
+        (self.roughness * self.roughness).max(MIN_ALPHA)
+    }
+
+    /// Width of the GGX distribution of the clearcoat layer.
+    fn clearcoat_alpha(&self) -> f32 {
+        (self.clearcoat_roughness * self.clearcoat_roughness).max(MIN_ALPHA)
+    }
+
+    /// Returns the probabilities of sampling the diffuse, specular and
+    /// clearcoat lobes, roughly following how much light each reflects.
+    fn lobe_probabilities(&self) -> (f32, f32, f32) {
+        let diffuse = (1.0 - self.metallic) * self.base_color.luminance();
+        let specular = self.f0().luminance().max(0.1);
+        let clearcoat = 0.25 * self.clearcoat;
+
+        let total = diffuse + specular + clearcoat;
+        (diffuse / total, specular / total, clearcoat / total)
+    }
+}
+
+impl Bsdf for PbrMaterial {
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> Color {
+        let normal = state.normal;
+        let n_dot_l = lightv.dot(normal);
+        let n_dot_v = eyev.dot(normal);
+        if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+            return Color::BLACK;
+        }
+
+        let h = (lightv + eyev).normalize();
+        let n_dot_h = normal.dot(h);
+        let v_dot_h = eyev.dot(h).max(0.0);
+
+        // specular layer
+        let alpha = self.alpha();
+        let fresnel = schlick(self.f0(), v_dot_h);
+        let geometry = smith_g1(n_dot_l, alpha) * smith_g1(n_dot_v, alpha);
+        let specular = fresnel * (ggx_d(n_dot_h, alpha) * geometry / (4.0 * n_dot_l * n_dot_v));
+
+        // the diffuse base only receives the light not reflected by the
+        // specular layer, which keeps the sum below one
+        let transmitted = Color::WHITE - schlick(self.f0(), n_dot_v);
+        let diffuse = self.base_color.blend(transmitted) * ((1.0 - self.metallic) / PI);
+
+        let base = diffuse + specular;
+        if self.clearcoat <= 0.0 {
+            return base;
+        }
+
+        let alpha = self.clearcoat_alpha();
+        let fresnel = schlick_scalar(0.04, v_dot_h) * self.clearcoat;
+        let geometry = smith_g1(n_dot_l, alpha) * smith_g1(n_dot_v, alpha);
+        let coat = fresnel * ggx_d(n_dot_h, alpha) * geometry / (4.0 * n_dot_l * n_dot_v);
+
+        // likewise, the base is seen through the clearcoat
+        let coated = schlick_scalar(0.04, n_dot_v) * self.clearcoat;
+        base * (1.0 - coated) + Color::WHITE * coat
+    }
+
+    /// One of the diffuse, specular and clearcoat lobes is chosen at random
+    /// and the direction is drawn from it.
+    fn sample(
+        &self,
+        state: &HitState<'_>,
+        eyev: Vec3,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let normal = state.normal;
+        let (diffuse, specular, _) = self.lobe_probabilities();
+        let (u, v) = sampler.next_2d();
+        let choice = sampler.next_f32();
+
+        let dir = if choice < diffuse {
+            let (x, y, z) = sampler::sample_cosine_hemisphere(u, v);
+            around(normal, x, y, z)
+        } else {
+            let alpha = if choice < diffuse + specular {
+                self.alpha()
+            } else {
+                self.clearcoat_alpha()
+            };
+
+            let h = sample_ggx(normal, alpha, u, v);
+            (-eyev).reflect(h)
+        };
+
+        if dir.dot(normal) <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            lightv: dir,
+            value: self.eval(state, dir, eyev),
+            pdf: self.pdf(state, dir, eyev),
+        })
+    }
+
+    fn pdf(&self, state: &HitState<'_>, lightv: Vec3, eyev: Vec3) -> f32 {
+        let normal = state.normal;
+        let n_dot_l = lightv.dot(normal);
+        if n_dot_l <= 0.0 || eyev.dot(normal) <= 0.0 {
+            return 0.0;
+        }
+
+        let h = (lightv + eyev).normalize();
+        let n_dot_h = normal.dot(h);
+        let v_dot_h = eyev.dot(h);
+        if v_dot_h <= 0.0 {
+            return 0.0;
+        }
+
+        // densities of the half vector, converted to the reflected direction
+        let microfacet = |alpha| ggx_d(n_dot_h, alpha) * n_dot_h / (4.0 * v_dot_h);
+
+        let (diffuse, specular, clearcoat) = self.lobe_probabilities();
+        diffuse * n_dot_l / PI
+            + specular * microfacet(self.alpha())
+            + clearcoat * microfacet(self.clearcoat_alpha())
+    }
+
+    fn albedo(&self, _state: &HitState<'_>) -> Color {
+        self.base_color
+    }
+
+    fn eq_dyn(&self, other: &dyn Bsdf) -> bool {
+        bsdf::eq_as(self, other)
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: [0.8, 0.8, 0.8].into(),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.1,
+        }
+    }
+}
+
+/// The GGX normal distribution function.
+fn ggx_d(n_dot_h: f32, alpha: f32) -> f32 {
+    if n_dot_h <= 0.0 {
+        return 0.0;
+    }
+
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Smith's masking function for the GGX distribution.
+fn smith_g1(n_dot_v: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    2.0 * n_dot_v / (n_dot_v + (a2 + (1.0 - a2) * n_dot_v * n_dot_v).sqrt())
+}
+
+/// Schlick's approximation of the Fresnel reflectance.
+fn schlick(f0: Color, cos: f32) -> Color {
+    let weight = (1.0 - cos).powi(5);
+    f0 * (1.0 - weight) + Color::WHITE * weight
+}
+
+/// Schlick's approximation for a colourless reflectance.
+fn schlick_scalar(f0: f32, cos: f32) -> f32 {
+    f0 + (1.0 - f0) * (1.0 - cos).powi(5)
+}
+
+/// Samples a microfacet normal from the GGX distribution, with a density
+/// of `D(h) (n·h)`.
+fn sample_ggx(normal: Vec3, alpha: f32, u: f32, v: f32) -> Vec3 {
+    let cos = ((1.0 - u) / (1.0 + (alpha * alpha - 1.0) * u)).sqrt();
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+
+    around(normal, sin * phi.cos(), sin * phi.sin(), cos)
+}
@@ -0,0 +1,325 @@
+//! Photon mapping of caustics.
+//!
+//! Caustics are the patterns of light focused by transparent objects, such
+//! as the bright spot below a glass ball. Direct lighting cannot find them,
+//! since the shadow rays towards the lights are blocked by the objects.
+//! Instead, photons are traced from the lights towards the transparent
+//! objects before rendering. Where a photon lands on an opaque surface
+//! after passing through a transparent object, it is stored in a
+//! [`PhotonMap`]. The light focused onto a point is then estimated from
+//! the density of the photons around it.
+
+use std::f32::consts::PI;
+
+use crate::{
+    lights::Light,
+    material::around,
+    ray::Ray,
+    sampler::Sampler,
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+/// Number of times a photon is reflected or refracted before it is lost.
+const MAX_BOUNCES: u32 = 8;
+
+/// A packet of light which landed on a surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Photon {
+    /// Where the photon landed.
+    pub position: Point3,
+    /// Unit vector pointing back in the direction the photon came from.
+    pub lightv: Vec3,
+    /// Power carried by the photon.
+    pub power: Color,
+}
+
+/// A collection of photons, arranged as a kd-tree to quickly find the
+/// photons around a point.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhotonMap {
+    /// The photons of a balanced kd-tree: every range of the vector has its
+    /// node in the middle and the subtrees to the left and right of it.
+    photons: Vec<Photon>,
+    /// The axis along which every node splits its subtrees.
+    axes: Vec<usize>,
+}
+
+impl PhotonMap {
+    /// Constructs a new `PhotonMap` from the photons.
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+
+        Self { photons, axes }
+    }
+
+    /// Returns the number of photons in the map.
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    /// Returns `true` if the map has no photons.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Returns all the photons within `radius` of `point`.
+    pub fn within(&self, point: Point3, radius: f32) -> Vec<&Photon> {
+        let mut found = Vec::new();
+        self.search(0, self.photons.len(), point, radius * radius, &mut found);
+        found
+    }
+
+    /// Collects the photons of the subtree in the range `lo..hi` whose
+    /// squared distance to `point` is at most `r2`.
+    fn search<'a>(
+        &'a self,
+        lo: usize,
+        hi: usize,
+        point: Point3,
+        r2: f32,
+        found: &mut Vec<&'a Photon>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+
+        let mid = (lo + hi) / 2;
+        let photon = &self.photons[mid];
+        if (photon.position - point).mag_sq() <= r2 {
+            found.push(photon);
+        }
+
+        let axis = self.axes[mid];
+        let d = point[axis] - photon.position[axis];
+        let (near, far) = if d < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+
+        self.search(near.0, near.1, point, r2, found);
+        if d * d <= r2 {
+            self.search(far.0, far.1, point, r2, found);
+        }
+    }
+}
+
+/// Arranges the photons as a kd-tree, splitting every range at the median
+/// along the axis in which the photons are spread the most.
+fn build(photons: &mut [Photon], axes: &mut [usize]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    let extent = |i: usize| {
+        let (min, max) = photons
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| {
+                (min.min(p.position[i]), max.max(p.position[i]))
+            });
+        max - min
+    };
+    let axis = (0..3)
+        .max_by(|&a, &b| extent(a).total_cmp(&extent(b)))
+        .unwrap_or(0);
+
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    axes[mid] = axis;
+
+    let (left, right) = photons.split_at_mut(mid);
+    let (left_axes, right_axes) = axes.split_at_mut(mid);
+    build(left, left_axes);
+    build(&mut right[1..], &mut right_axes[1..]);
+}
+
+/// Traces `count` photons from every light towards the transparent objects
+/// of the world and collects those which land on an opaque surface after
+/// being reflected or refracted.
+pub(crate) fn trace_caustics(world: &World, count: u32, sampler: &mut Sampler) -> PhotonMap {
+    let targets: Vec<(Point3, f32)> = world
+        .objects
+        .iter()
+        .filter(|obj| obj.material().transparency() > 0.0 && obj.medium().is_none())
+        .map(|obj| obj.bounds())
+        .collect();
+    if targets.is_empty() || count == 0 {
+        return PhotonMap::default();
+    }
+
+    let mut photons = Vec::new();
+    for light in &world.lights {
+        for _ in 0..count {
+            let emitted = match light {
+                Light::Directional(l) => emit_parallel(world, &targets, l.direction, sampler)
+                    .map(|(ray, area)| (ray, Some(l.intensity * (area / count as f32)))),
+                _ => {
+                    let origin = match light {
+                        Light::Point(l) => l.pos,
+                        Light::Spot(l) => l.pos,
+                        Light::Area(l) => {
+                            let (u, v) = sampler.next_2d();
+                            l.point_on_light(u, v)
+                        }
+                        Light::Directional(_) => unreachable!(),
+                    };
+                    emit_towards(&targets, origin, sampler).map(|ray| (ray, None))
+                }
+            };
+
+            if let Some((ray, power)) = emitted {
+                photons.extend(trace_photon(world, light, ray, power, count, sampler));
+            }
+        }
+    }
+
+    PhotonMap::new(photons)
+}
+
+/// Samples a ray from `origin` towards one of the targets. The direction of
+/// the ray is unnormalized; its length is the reciprocal of the density with
+/// which the direction was picked, the solid angle the photon stands for.
+fn emit_towards(targets: &[(Point3, f32)], origin: Point3, sampler: &mut Sampler) -> Option<Ray> {
+    // every target is seen from the origin in a cone of directions
+    let cones: Vec<(Vec3, f32)> = targets
+        .iter()
+        .map(|&(centre, radius)| {
+            let v = centre - origin;
+            let d2 = v.mag_sq();
+            let cos_max = if d2 <= radius * radius {
+                -1.0
+            } else {
+                (1.0 - radius * radius / d2).sqrt()
+            };
+            let axis = if d2 > 0.0 {
+                v.normalize()
+            } else {
+                Vec3::new(0., 0., 1.)
+            };
+            (axis, cos_max)
+        })
+        .collect();
+
+    let k = ((sampler.next_f32() * cones.len() as f32) as usize).min(cones.len() - 1);
+    let (axis, cos_max) = cones[k];
+    let (u, v) = sampler.next_2d();
+    let cos = 1.0 - u * (1.0 - cos_max);
+    let sin = (1.0 - cos * cos).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    let dir = around(axis, sin * phi.cos(), sin * phi.sin(), cos);
+
+    // the density of the direction, summed over all the cones containing it
+    let pdf = cones
+        .iter()
+        .filter(|(axis, cos_max)| dir.dot(*axis) >= *cos_max)
+        .map(|(_, cos_max)| 1.0 / (2.0 * PI * (1.0 - cos_max)))
+        .sum::<f32>()
+        / cones.len() as f32;
+
+    (pdf > 0.0).then(|| Ray::new(origin, dir * (1.0 / pdf)))
+}
+
+/// Samples a ray of a light shining along `direction` which hits one of the
+/// targets. Returns the ray along with the area the photon stands for.
+fn emit_parallel(
+    world: &World,
+    targets: &[(Point3, f32)],
+    direction: Vec3,
+    sampler: &mut Sampler,
+) -> Option<(Ray, f32)> {
+    // every target casts a disk onto the plane perpendicular to the light
+    let k = ((sampler.next_f32() * targets.len() as f32) as usize).min(targets.len() - 1);
+    let (centre, radius) = targets[k];
+    let (u, v) = sampler.next_2d();
+    let r = radius * u.sqrt();
+    let phi = 2.0 * PI * v;
+    let (t, b) = direction.basis();
+    let point = centre + t * (r * phi.cos()) + b * (r * phi.sin());
+
+    let pdf = targets
+        .iter()
+        .filter(|&&(c, radius)| {
+            let offset = point - c;
+            let along = offset.dot(direction);
+            (offset - direction * along).mag_sq() <= radius * radius
+        })
+        .map(|(_, radius)| 1.0 / (PI * radius * radius))
+        .sum::<f32>()
+        / targets.len() as f32;
+
+    // start the ray behind all the objects
+    let behind = world
+        .objects
+        .iter()
+        .map(|obj| {
+            let (c, radius) = obj.bounds();
+            (point - c).dot(direction) + radius
+        })
+        .fold(0.0, f32::max);
+
+    (pdf > 0.0).then(|| (Ray::new(point - direction * behind, direction), 1.0 / pdf))
+}
+
+/// Follows a photon through the transparent objects. Returns the photon if
+/// it lands on an opaque surface after being reflected or refracted at
+/// least once.
+///
+/// The power of photons from lights with a position is only known once they
+/// hit the first surface: it is the light arriving there, spread over the
+/// area covered by the solid angle of the photon.
+fn trace_photon(
+    world: &World,
+    light: &Light,
+    mut ray: Ray,
+    mut power: Option<Color>,
+    count: u32,
+    sampler: &mut Sampler,
+) -> Option<Photon> {
+    let mut specular = false;
+
+    for _ in 0..MAX_BOUNCES {
+        let mut xs = world.intersect(&ray);
+        let state = xs.prepare_hit(&ray)?;
+        let power = power.get_or_insert_with(|| {
+            let solid_angle = ray.dir.mag();
+            let distance = state.t * solid_angle;
+            let arriving = light
+                .samples(state.point, sampler)
+                .iter()
+                .fold(Color::BLACK, |acc, s| acc + s.intensity);
+            arriving * (distance * distance * solid_angle / count as f32)
+        });
+
+        if let Some(obj) = state.within {
+            *power = power.blend(obj.material().attenuation(state.t * ray.dir.mag()));
+        }
+        if power.max_component() <= 0.0 {
+            return None;
+        }
+
+        let transparency = state.obj.material().transparency();
+        if transparency <= 0.0 || sampler.next_f32() >= transparency {
+            return specular.then(|| Photon {
+                position: state.point,
+                lightv: state.eyev.normalize(),
+                power: *power,
+            });
+        }
+
+        specular = true;
+        let refracted = match state.refractv() {
+            Some(dir) if sampler.next_f32() >= state.schlick() => Some(dir),
+            _ => None,
+        };
+        ray = match refracted {
+            Some(dir) => Ray::new(state.under_point, dir),
+            None => Ray::new(state.over_point, state.reflectv()),
+        };
+    }
+
+    None
+}
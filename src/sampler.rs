@@ -0,0 +1,128 @@
+//! A small, deterministic pseudo-random number generator used for
+//! stochastic sampling.
+//!
+//! We use the PCG32 generator because it is tiny, fast and has good
+//! statistical properties. Samplers are seeded from the pixel and sample
+//! index so that renders are reproducible.
+//!
+//! This module also contains the warping functions which map uniform
+//! samples onto other domains.
+
+use std::f32::consts::{FRAC_PI_4, PI};
+
+/// Multiplier of the underlying linear congruential generator.
+const PCG_MULT: u64 = 6364136223846793005;
+
+/// A PCG32 random number generator.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+    inc: u64,
+}
+
+impl Sampler {
+    /// Constructs a new `Sampler` from the given seed.
+    pub fn new(seed: u64) -> Self {
+        let mut sampler = Self {
+            state: 0,
+            inc: (splitmix64(seed) << 1) | 1,
+        };
+
+        sampler.next_u32();
+        sampler.state = sampler.state.wrapping_add(seed);
+        sampler.next_u32();
+
+        sampler
+    }
+
+    /// Constructs a `Sampler` for the given sample of a pixel.
+    pub fn for_pixel(x: u32, y: u32, index: u32) -> Self {
+        let key = (u64::from(y) << 32 | u64::from(x)) ^ splitmix64(u64::from(index));
+        Self::new(key)
+    }
+
+    /// Returns the next uniformly distributed `u32`.
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(PCG_MULT).wrapping_add(self.inc);
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+
+        xorshifted.rotate_right(rot)
+    }
+
+    /// Returns the next uniformly distributed `f32` in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        // use the upper 24 bits so that the result is exactly representable
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Returns a pair of uniformly distributed values in `[0, 1)`.
+    pub fn next_2d(&mut self) -> (f32, f32) {
+        let u = self.next_f32();
+        let v = self.next_f32();
+        (u, v)
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Maps a uniform sample in `[0, 1)^2` onto the unit disk.
+/// Uses the concentric mapping which preserves the stratification of the samples.
+pub fn sample_disk(u: f32, v: f32) -> (f32, f32) {
+    let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, PI / 2.0 - FRAC_PI_4 * (a / b))
+    };
+
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Maps a uniform sample in `[0, 1)^2` onto a regular polygon with `sides`
+/// vertices inscribed in the unit circle.
+pub fn sample_polygon(u: f32, v: f32, sides: u32) -> (f32, f32) {
+    // pick one of the triangles fanning out from the centre and reuse
+    // the remainder of `u` to sample a point inside it.
+    let n = sides as f32;
+    let scaled = u * n;
+    let i = scaled.floor().min(n - 1.0);
+    let u = scaled - i;
+
+    let step = 2.0 * PI / n;
+    let (a0, a1) = (i * step, (i + 1.0) * step);
+
+    // uniform sampling of the triangle (centre, p0, p1)
+    let su = u.sqrt();
+    let (b0, b1) = (su * (1.0 - v), su * v);
+
+    (b0 * a0.cos() + b1 * a1.cos(), b0 * a0.sin() + b1 * a1.sin())
+}
+
+/// Maps a uniform sample in `[0, 1)^2` onto the hemisphere around `+z`,
+/// with a density proportional to the cosine of the angle to `+z`.
+/// The density of the returned direction `w` is `w.z / π`.
+pub fn sample_cosine_hemisphere(u: f32, v: f32) -> (f32, f32, f32) {
+    let (x, y) = sample_disk(u, v);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+    (x, y, z)
+}
+
+/// Scrambles the bits of `x`. Used to decorrelate nearby seeds.
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
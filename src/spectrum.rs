@@ -0,0 +1,80 @@
+//! Conversions from spectral quantities to RGB colours.
+//!
+//! Spectra are integrated against the CIE 1931 colour matching functions,
+//! using the analytic multi-lobe Gaussian fit by Wyman, Sloan and Shirley,
+//! and converted from XYZ to linear RGB with the Rec. 709 (sRGB) primaries.
+
+use crate::Color;
+
+/// Second radiation constant `h * c / k` in metre kelvin.
+const C2: f64 = 1.4387769e-2;
+
+/// Range and step of the wavelengths used for integration, in nanometres.
+const LAMBDA_MIN: u32 = 360;
+const LAMBDA_MAX: u32 = 830;
+const LAMBDA_STEP: u32 = 5;
+
+/// Returns the colour of a blackbody radiator at the temperature `kelvin`,
+/// i.e. a point on the Planckian locus.
+///
+/// The colour is scaled to a luminance of one. Components which fall
+/// outside the RGB gamut are clamped to zero.
+pub fn blackbody(kelvin: f32) -> Color {
+    let kelvin = f64::from(kelvin.max(1.0));
+
+    let xyz = integrate(|lambda| {
+        // Planck's law, up to a constant factor which cancels out when normalizing
+        let l = lambda * 1e-9;
+        1.0 / (l.powi(5) * ((C2 / (l * kelvin)).exp() - 1.0))
+    });
+
+    let [x, y, z] = xyz;
+    let rgb = xyz_to_rgb([x / y, 1.0, z / y]);
+
+    rgb.map(|c| c.max(0.0) as f32).into()
+}
+
+/// Integrates the spectrum against the colour matching functions and
+/// returns the resulting XYZ tristimulus values.
+fn integrate(spectrum: impl Fn(f64) -> f64) -> [f64; 3] {
+    let mut xyz = [0.0; 3];
+
+    for lambda in (LAMBDA_MIN..=LAMBDA_MAX).step_by(LAMBDA_STEP as usize) {
+        let lambda = f64::from(lambda);
+        let power = spectrum(lambda);
+        let cmf = cie_1931(lambda);
+
+        for (acc, c) in xyz.iter_mut().zip(cmf) {
+            *acc += power * c;
+        }
+    }
+
+    xyz
+}
+
+/// Returns the CIE 1931 colour matching functions at `lambda` nanometres.
+fn cie_1931(lambda: f64) -> [f64; 3] {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+
+    [x, y, z]
+}
+
+/// A Gaussian with different widths on either side of its mean.
+fn lobe(x: f64, mean: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if x < mean { sigma_low } else { sigma_high };
+    let t = (x - mean) / sigma;
+
+    (-0.5 * t * t).exp()
+}
+
+/// Converts XYZ tristimulus values to linear RGB with the Rec. 709 primaries.
+fn xyz_to_rgb([x, y, z]: [f64; 3]) -> [f64; 3] {
+    [
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.969266 * x + 1.8760108 * y + 0.041556 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    ]
+}
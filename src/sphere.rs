@@ -1,9 +1,12 @@
 //! Implementation of the sphere object.
 
+use std::f32::consts::PI;
+
 use crate::{
     hit_list::{HitList, HitRec},
     material::Material,
     matrix::Mat4,
+    medium::Medium,
     ray::Ray,
     vec3::{Point3, Vec3},
 };
@@ -16,6 +19,7 @@ pub struct Sphere {
     transform: Mat4,
     transform_inv: Mat4,
     material: Material,
+    medium: Option<Medium>,
 }
 
 impl Sphere {
@@ -25,6 +29,7 @@ impl Sphere {
             transform: Mat4::identity(),
             transform_inv: Mat4::identity(),
             material,
+            medium: None,
         }
     }
 
@@ -41,6 +46,24 @@ impl Sphere {
         self
     }
 
+    /// Fills the sphere with a participating medium. The surface of the
+    /// sphere then only bounds the medium and is itself invisible.
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    /// Gets the medium inside the sphere, if any.
+    pub fn medium(&self) -> Option<&Medium> {
+        self.medium.as_ref()
+    }
+
+    /// Returns `true` if the point lies inside the sphere.
+    pub fn contains(&self, point: Point3) -> bool {
+        let object_point = &self.transform_inv * point;
+        (object_point - Point3::default()).mag_sq() < 1.0
+    }
+
     /// Intersect the ray with the sphere.
     /// Returns a `HitList` which stores the point and object of intersections.
     pub fn intersect(&self, r: &Ray) -> HitList<'_> {
@@ -84,6 +107,90 @@ impl Sphere {
         world_normal.normalize()
     }
 
+    /// Returns a unit tangent at a point on the sphere, following the
+    /// lines of latitude of the untransformed sphere around the `y` axis.
+    /// Near the poles, where those lines vanish, any tangent is returned.
+    pub fn tangent_at(&self, point: Point3) -> Vec3 {
+        let normal = self.normal_at(point);
+        let object_point = &self.transform_inv * point;
+        let object_tangent = Vec3::new(object_point.z(), 0.0, -object_point.x());
+
+        let tangent = &self.transform * object_tangent;
+        // remove any part along the normal left by a non-uniform scaling
+        let tangent = tangent - normal * tangent.dot(normal);
+        if tangent.mag_sq() < 1e-12 {
+            return normal.basis().0;
+        }
+
+        tangent.normalize()
+    }
+
+    /// Returns the texture coordinates `(u, v)` in `[0, 1]^2` of a point on
+    /// the sphere, using a spherical mapping of the untransformed sphere.
+    /// `u` runs around the `y` axis and `v` from the bottom to the top.
+    pub fn uv_at(&self, point: Point3) -> (f32, f32) {
+        let p = &self.transform_inv * point;
+        let radius = (p - Point3::default()).mag();
+        let theta = p.x().atan2(p.z());
+        let phi = (p.y() / radius).clamp(-1.0, 1.0).acos();
+
+        let u = 1.0 - (theta / (2.0 * PI) + 0.5);
+        let v = 1.0 - phi / PI;
+        (u, v)
+    }
+
+    /// Returns the point on the surface of the sphere for the sample
+    /// `(u, v)` in `[0, 1)^2`. The points are uniformly distributed on the
+    /// untransformed sphere; their density is given by `surface_pdf()`.
+    pub fn point_on_surface(&self, u: f32, v: f32) -> Point3 {
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+
+        &self.transform * Point3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    /// Returns the density, with respect to area, with which
+    /// `point_on_surface()` picks `point`.
+    pub fn surface_pdf(&self, point: Point3) -> f32 {
+        // a patch of the unit sphere is stretched by the determinant of the
+        // transform, divided by how much the normal is shortened.
+        let object_normal = (&self.transform_inv * point) - Point3::default();
+        let mut normal = &self.transform_inv.transpose() * object_normal;
+        normal[3] = 0.0;
+
+        let scale = self.transform.determinant().abs() * normal.mag();
+        1.0 / (4.0 * PI * scale)
+    }
+
+    /// Returns the centre and radius of the smallest sphere enclosing the
+    /// object.
+    pub fn bounds(&self) -> (Point3, f32) {
+        let centre = &self.transform * Point3::default();
+
+        // the radius is the largest stretch of the transform, the square
+        // root of the largest eigenvalue of `AᵀA`
+        let axes =
+            [(1., 0., 0.), (0., 1., 0.), (0., 0., 1.)].map(|a| &self.transform * Vec3::from(a));
+        let m = |i: usize, j: usize| axes[i].dot(axes[j]);
+        let q = (m(0, 0) + m(1, 1) + m(2, 2)) / 3.0;
+        let off = m(0, 1).powi(2) + m(0, 2).powi(2) + m(1, 2).powi(2);
+        let p2 = (m(0, 0) - q).powi(2) + (m(1, 1) - q).powi(2) + (m(2, 2) - q).powi(2) + 2.0 * off;
+        let largest = if p2 < 1e-12 {
+            q
+        } else {
+            let p = (p2 / 6.0).sqrt();
+            let b = |i: usize, j: usize| (m(i, j) - if i == j { q } else { 0.0 }) / p;
+            let det = b(0, 0) * (b(1, 1) * b(2, 2) - b(1, 2) * b(2, 1))
+                - b(0, 1) * (b(1, 0) * b(2, 2) - b(1, 2) * b(2, 0))
+                + b(0, 2) * (b(1, 0) * b(2, 1) - b(1, 1) * b(2, 0));
+            let phi = (det / 2.0).clamp(-1.0, 1.0).acos() / 3.0;
+            q + 2.0 * p * phi.cos()
+        };
+
+        (centre, largest.sqrt())
+    }
+
     /// Gets a reference to the material.
     pub fn material(&self) -> &Material {
         &self.material
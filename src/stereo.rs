@@ -0,0 +1,147 @@
+//! A stereo rig which renders the scene from two horizontally offset eyes.
+//!
+//! The rig wraps a [`Camera`] describing the centre between the eyes. The
+//! left and right cameras are derived from it, offset by half the
+//! interocular distance each, and converge according to [`Convergence`].
+
+use crate::{camera::Camera, world::World};
+
+/// Describes how the eyes of a [`StereoCamera`] converge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Convergence {
+    /// The eyes look straight ahead and never converge.
+    /// Objects at infinity appear at the screen plane.
+    Parallel,
+    /// Both eyes are rotated inwards to look at the convergence point.
+    /// Simple, but introduces vertical parallax at the sides of the image.
+    ToeIn,
+    /// The eyes look straight ahead but their canvases are shifted so that
+    /// the views coincide at the convergence distance.
+    OffAxis,
+}
+
+/// Describes how the two views are arranged in a single image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StereoLayout {
+    /// The left view is on the left and the right view on the right.
+    SideBySide,
+    /// The left view is on top of the right view.
+    TopBottom,
+}
+
+/// Which of the two eyes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Eye {
+    /// The left eye.
+    Left,
+    /// The right eye.
+    Right,
+}
+
+/// A pair of cameras offset horizontally to render stereoscopic images.
+#[derive(Debug, Clone)]
+pub struct StereoCamera {
+    /// The camera placed between the eyes.
+    camera: Camera,
+    /// Distance between the eyes in world-space.
+    interocular: f32,
+    /// How the eyes converge.
+    convergence: Convergence,
+    /// Distance from the camera to the plane where the views coincide.
+    convergence_distance: f32,
+}
+
+impl StereoCamera {
+    /// Constructs a new `StereoCamera` centred on `camera`.
+    /// The eyes are parallel by default.
+    pub fn new(camera: Camera, interocular: f32) -> Self {
+        Self {
+            camera,
+            interocular,
+            convergence: Convergence::Parallel,
+            convergence_distance: 1.0,
+        }
+    }
+
+    /// Sets how the eyes converge and the distance at which they converge.
+    /// The distance is ignored for parallel eyes.
+    pub fn with_convergence(mut self, convergence: Convergence, distance: f32) -> Self {
+        self.convergence = convergence;
+        self.convergence_distance = distance;
+        self
+    }
+
+    /// Returns the camera used for the given eye.
+    pub fn eye(&self, eye: Eye) -> Camera {
+        // camera looks toward -z, so +x is to the left
+        let offset = match eye {
+            Eye::Left => self.interocular / 2.0,
+            Eye::Right => -self.interocular / 2.0,
+        };
+
+        let d = self.convergence_distance;
+        let transform = self
+            .camera
+            .transform()
+            .clone()
+            .translate((-offset, 0., 0.).into());
+
+        match self.convergence {
+            Convergence::Parallel => self.camera.clone().with_transform(transform),
+            Convergence::ToeIn => {
+                // rotate the eye so that the convergence point lies on its
+                // viewing direction
+                let angle = -offset.atan2(d);
+                self.camera
+                    .clone()
+                    .with_transform(transform.rotate_y(angle))
+            }
+            Convergence::OffAxis => {
+                // the convergence point projects onto the canvas at `-offset / d`
+                self.camera
+                    .clone()
+                    .with_transform(transform)
+                    .with_lens_shift(-offset / d, 0.0)
+            }
+        }
+    }
+
+    /// Renders the scene once for each eye.
+    pub fn render(&self, world: &World) -> StereoImage {
+        StereoImage {
+            left: self.eye(Eye::Left).render(world),
+            right: self.eye(Eye::Right).render(world),
+        }
+    }
+}
+
+/// The views rendered by a [`StereoCamera`].
+/// The views can be saved to separate files or combined into one image.
+#[derive(Debug, Clone)]
+pub struct StereoImage {
+    /// The view of the left eye.
+    pub left: image::Rgb32FImage,
+    /// The view of the right eye.
+    pub right: image::Rgb32FImage,
+}
+
+impl StereoImage {
+    /// Combines both views into a single image.
+    pub fn combine(&self, layout: StereoLayout) -> image::Rgb32FImage {
+        let (w, h) = self.left.dimensions();
+        let (right_x, right_y, width, height) = match layout {
+            StereoLayout::SideBySide => (w, 0, w * 2, h),
+            StereoLayout::TopBottom => (0, h, w, h * 2),
+        };
+
+        let mut canvas = image::Rgb32FImage::new(width, height);
+        for (x, y, pixel) in self.left.enumerate_pixels() {
+            canvas.put_pixel(x, y, *pixel);
+        }
+        for (x, y, pixel) in self.right.enumerate_pixels() {
+            canvas.put_pixel(right_x + x, right_y + y, *pixel);
+        }
+
+        canvas
+    }
+}
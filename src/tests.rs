@@ -2,12 +2,27 @@
 //! They are in a separate module so that the whole library
 //! is not recompiled for running tests.
 
+mod anisotropic;
+mod aov;
 mod camera;
+mod filter;
+mod ies;
+mod integrator;
+mod lights;
 mod material;
 mod matrix;
+mod medium;
+mod oren_nayar;
+mod output;
+mod pbr;
+mod photon_map;
 mod ray;
+mod sampler;
+mod spectrum;
 mod sphere;
+mod stereo;
 mod vec3;
+mod volume;
 mod world;
 
 use approx::assert_relative_eq;
@@ -29,6 +44,9 @@ fn color() {
     let b: Color = [0.9, 1.0, 0.1].into();
 
     assert_relative_eq!(a.blend(b), [0.9, 0.2, 0.04].into());
+
+    let white: Color = [1.0, 1.0, 1.0].into();
+    assert_relative_eq!(white.luminance(), 1.0);
 }
 
 mod hit_list {
@@ -79,3 +97,28 @@ mod hit_list {
         assert_eq!(hit_state.normal, (0., 0., -1.).into());
     }
 }
+
+mod tile {
+    use crate::tile::{self, Tile};
+
+    #[test]
+    fn split_covers_image() {
+        let tiles = Tile::split(10, 7, 4);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[2].x0, 8);
+        assert_eq!(tiles[2].width, 2);
+        assert_eq!(tiles[5].height, 3);
+
+        let pixels: usize = tiles.iter().map(|t| t.pixels().count()).sum();
+        assert_eq!(pixels, 70);
+    }
+
+    #[test]
+    fn parallel_results_keep_order() {
+        let items: Vec<u32> = (0..100).collect();
+        let results = tile::for_each_parallel(&items, 8, |i| i * 2);
+
+        assert_eq!(results, (0..100).map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
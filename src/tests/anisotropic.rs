@@ -0,0 +1,74 @@
+use std::f32::consts::PI;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    anisotropic::AshikhminShirley,
+    bsdf::Bsdf,
+    hit_list::{HitRec, HitState},
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::Vec3,
+    Color,
+};
+
+/// Returns the hit at the front of the sphere, where the normal is `-z`.
+fn front(sphere: &Sphere) -> HitState<'_> {
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    HitRec {
+        t: 4.0,
+        obj: sphere,
+    }
+    .prepare_computations(&r)
+}
+
+#[test]
+fn highlight_follows_tangent() {
+    let sphere = Sphere::default();
+    let state = front(&sphere);
+    let (t, n) = (state.tangent, state.normal);
+    let b = n.cross(t);
+
+    // blurry along the tangent, sharp across it
+    let m = AshikhminShirley::new(Color::BLACK, [0.9, 0.9, 0.9].into(), 10.0, 1000.0);
+
+    let tilted = |axis: Vec3| (n + axis * 0.2).normalize();
+    let along = m.eval(&state, tilted(t), n).into_inner()[0];
+    let across = m.eval(&state, tilted(b), n).into_inner()[0];
+    assert!(along > 10.0 * across);
+}
+
+#[test]
+fn sampling_matches_pdf() {
+    let sphere = Sphere::default();
+    let state = front(&sphere);
+    let n = state.normal;
+    let eyev = (n + state.tangent * 0.5).normalize();
+    let m = AshikhminShirley::new([0.5, 0.4, 0.3].into(), [0.3, 0.3, 0.3].into(), 20.0, 200.0);
+
+    let mut s = Sampler::new(5);
+    let mut importance = Color::BLACK;
+    let samples = 20_000;
+    for _ in 0..samples {
+        if let Some(bs) = m.sample(&state, eyev, &mut s) {
+            assert_relative_eq!(bs.pdf, m.pdf(&state, bs.lightv, eyev), max_relative = 1e-3);
+            importance = importance + bs.value * (bs.lightv.dot(n) / bs.pdf);
+        }
+    }
+    let importance = importance * (1.0 / samples as f32);
+
+    let (t, b) = (state.tangent, n.cross(state.tangent));
+    let mut uniform = Color::BLACK;
+    let count = 400_000;
+    for _ in 0..count {
+        let (u, v) = s.next_2d();
+        let r = (1.0 - u * u).sqrt();
+        let phi = 2.0 * PI * v;
+        let l = t * (r * phi.cos()) + b * (r * phi.sin()) + n * u;
+        uniform = uniform + m.eval(&state, l, eyev) * (u * 2.0 * PI);
+    }
+    let uniform = uniform * (1.0 / count as f32);
+
+    assert_relative_eq!(importance, uniform, max_relative = 0.05);
+}
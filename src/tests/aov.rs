@@ -0,0 +1,74 @@
+use std::f32::consts::FRAC_PI_2;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    aov::Aovs,
+    camera::Camera,
+    material::Material,
+    matrix::Mat4,
+    oren_nayar::OrenNayar,
+    vec3::{Point3, Vec3},
+};
+
+use super::world::default_world;
+
+fn camera() -> Camera {
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::default(),
+        Vec3::new(0., 1., 0.),
+    );
+    Camera::new(11, 11, FRAC_PI_2).with_transform(transform)
+}
+
+#[test]
+fn aovs_of_hit() {
+    let world = default_world();
+    let result = camera().render_aovs(&world);
+    let aovs = result.aovs(5, 5);
+
+    assert_relative_eq!(aovs.depth, 4.0, epsilon = 1e-4);
+    assert_relative_eq!(aovs.normal, Vec3::new(0., 0., -1.), epsilon = 1e-4);
+    assert_relative_eq!(aovs.position, Point3::new(0., 0., -1.), epsilon = 1e-4);
+    assert_relative_eq!(aovs.albedo, [0.8, 1.0, 0.6].into());
+    assert_eq!(aovs.object, Some(0));
+    assert_relative_eq!(aovs.uv.1, 0.5, epsilon = 1e-4);
+
+    // the colour is the same as without the output variables
+    assert_eq!(result.color, camera().render(&world));
+}
+
+#[test]
+fn aovs_of_background() {
+    let world = default_world();
+    let result = camera().render_aovs(&world);
+
+    assert_eq!(result.aovs(0, 0), Aovs::background());
+    assert_eq!(result.object.get_pixel(0, 0).0, [-1.0]);
+    assert!(result.depth.get_pixel(0, 0).0[0].is_infinite());
+}
+
+#[test]
+fn depth_is_distance_to_camera() {
+    let world = default_world();
+    let cam = camera();
+    let result = cam.render_aovs(&world);
+
+    for (x, y) in [(4, 5), (6, 5), (5, 4)] {
+        let aovs = result.aovs(x, y);
+        assert!(aovs.object.is_some());
+        let distance = (aovs.position - Point3::new(0., 0., -5.)).mag();
+        assert_relative_eq!(aovs.depth, distance, epsilon = 1e-4);
+    }
+}
+
+#[test]
+fn albedo_comes_from_the_bsdf() {
+    let mut world = default_world();
+    let bsdf = OrenNayar::new([0.2, 0.4, 0.6].into(), 0.3);
+    *world.objects[0].material_mut() = Material::default().with_bsdf(bsdf);
+
+    let result = camera().render_aovs(&world);
+    assert_relative_eq!(result.aovs(5, 5).albedo, [0.2, 0.4, 0.6].into());
+}
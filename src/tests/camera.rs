@@ -1,9 +1,14 @@
-use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+use std::{
+    f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4, PI},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
 use approx::assert_relative_eq;
 
 use crate::{
-    camera::Camera,
+    camera::{Camera, FisheyeMapping, Projection, RenderEvent},
+    filter::Filter,
     matrix::Mat4,
     vec3::{Point3, Vec3},
     Color,
@@ -62,3 +67,287 @@ fn render_default_world() {
         Color(image::Rgb::<f32>([0.38066, 0.475826, 0.28549]))
     );
 }
+
+#[test]
+fn render_with_multiple_samples() {
+    // a flat background is unaffected by the number of samples and the filter.
+    let world = default_world();
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::new(0., 5., 0.),
+        Vec3::new(0., 1., 0.),
+    );
+
+    let filters = [
+        Filter::new_box(0.5),
+        Filter::new_tent(1.5),
+        Filter::new_gaussian(2.0, 2.0),
+    ];
+
+    for filter in filters {
+        let cam = Camera::new(4, 4, FRAC_PI_4)
+            .with_transform(transform.clone())
+            .with_samples(4)
+            .with_filter(filter);
+
+        let image = cam.render(&world);
+        assert_relative_eq!(Color(*image.get_pixel(0, 0)), Color::BLACK);
+    }
+}
+
+#[test]
+fn wide_filter_blends_neighbours() {
+    let world = default_world();
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::default(),
+        Vec3::new(0., 1., 0.),
+    );
+
+    let narrow = Camera::new(11, 11, FRAC_PI_2).with_transform(transform.clone());
+    let wide = Camera::new(11, 11, FRAC_PI_2)
+        .with_transform(transform)
+        .with_filter(Filter::new_tent(2.0));
+
+    // the edge of the sphere is a hard edge with a box filter, and is
+    // softened by the wider filter.
+    let narrow = narrow.render(&world);
+    let wide = wide.render(&world);
+
+    let row = 5;
+    let edge = (0..11)
+        .find(|&x| narrow.get_pixel(x, row)[0] > 0.0)
+        .unwrap();
+
+    assert_eq!(narrow.get_pixel(edge - 1, row)[0], 0.0);
+    assert!(wide.get_pixel(edge - 1, row)[0] > 0.0);
+}
+
+#[test]
+fn lens_rays_converge_on_focal_plane() {
+    let cam = Camera::new(201, 101, FRAC_PI_2)
+        .with_aperture(0.5)
+        .with_focal_distance(4.0);
+
+    let centre = cam.ray_for_pixel(100, 50);
+    assert_relative_eq!(centre.orig, (0.0, 0.0, 0.0).into());
+
+    for lens in [(0.1, 0.2), (0.9, 0.5), (0.4, 0.95)] {
+        let r = cam.ray_for_sample(100.5, 50.5, lens);
+
+        // the origin lies on the lens
+        assert_relative_eq!(r.orig.z(), 0.0);
+        let (x, y) = (r.orig.x(), r.orig.y());
+        assert!((x * x + y * y).sqrt() <= 0.5 + 1e-5);
+
+        // and the ray passes through the focus point
+        let t = -4.0 / r.dir.z();
+        assert_relative_eq!(r.pos(t), (0.0, 0.0, -4.0).into(), epsilon = 1e-4);
+    }
+}
+
+#[test]
+fn pinhole_ignores_lens_sample() {
+    let cam = Camera::new(201, 101, FRAC_PI_2).with_focal_distance(4.0);
+
+    let r = cam.ray_for_sample(0.5, 0.5, (0.9, 0.1));
+    assert_relative_eq!(r.orig, (0.0, 0.0, 0.0).into());
+    assert_relative_eq!(r.dir, (0.66519, 0.33259, -0.66851).into());
+}
+
+#[test]
+fn orthographic_rays_are_parallel() {
+    let cam = Camera::new(200, 100, FRAC_PI_2)
+        .with_projection(Projection::Orthographic { view_width: 4.0 });
+
+    let centre = cam.ray_for_pixel(100, 50);
+    let corner = cam.ray_for_sample(0.0, 0.0, (0.5, 0.5));
+
+    assert_relative_eq!(centre.dir, (0., 0., -1.).into());
+    assert_relative_eq!(corner.dir, (0., 0., -1.).into());
+    assert_relative_eq!(corner.orig, (2.0, 1.0, 0.0).into());
+}
+
+#[test]
+fn equirectangular_covers_the_sphere() {
+    let cam = Camera::new(360, 180, FRAC_PI_2).with_projection(Projection::Equirectangular);
+
+    let forward = cam.ray_for_sample(180.0, 90.0, (0.5, 0.5));
+    let right = cam.ray_for_sample(270.0, 90.0, (0.5, 0.5));
+    let behind = cam.ray_for_sample(0.0, 90.0, (0.5, 0.5));
+    let up = cam.ray_for_sample(180.0, 0.0, (0.5, 0.5));
+
+    // +x is to the left of the camera
+    assert_relative_eq!(forward.dir, (0., 0., -1.).into());
+    assert_relative_eq!(right.dir, (-1., 0., 0.).into());
+    assert_relative_eq!(behind.dir, (0., 0., 1.).into());
+    assert_relative_eq!(up.dir, (0., 1., 0.).into());
+}
+
+#[test]
+fn fisheye_edge_matches_field_of_view() {
+    for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+        let cam = Camera::new(100, 100, PI).with_projection(Projection::Fisheye(mapping));
+
+        let centre = cam.ray_for_sample(50.0, 50.0, (0.5, 0.5));
+        let edge = cam.ray_for_sample(100.0, 50.0, (0.5, 0.5));
+
+        assert_relative_eq!(centre.dir, (0., 0., -1.).into());
+        assert_relative_eq!(edge.dir, (-1., 0., 0.).into());
+
+        assert!(cam.covers(50.0, 0.0));
+        assert!(!cam.covers(0.0, 0.0));
+    }
+}
+
+#[test]
+fn equisolid_compresses_the_edge() {
+    let equidistant =
+        Camera::new(100, 100, PI).with_projection(Projection::Fisheye(FisheyeMapping::Equidistant));
+    let equisolid =
+        Camera::new(100, 100, PI).with_projection(Projection::Fisheye(FisheyeMapping::Equisolid));
+
+    // halfway to the edge, equidistant is at 45 degrees but equisolid
+    // is at 2 * asin(sin(45) / 2).
+    let a = equidistant.ray_for_sample(75.0, 50.0, (0.5, 0.5));
+    let b = equisolid.ray_for_sample(75.0, 50.0, (0.5, 0.5));
+
+    assert_relative_eq!(a.dir, (-FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2).into());
+    let theta = 2.0 * (FRAC_1_SQRT_2 / 2.0).asin();
+    assert_relative_eq!(b.dir, (-theta.sin(), 0., -theta.cos()).into());
+}
+
+#[test]
+fn threaded_render_is_identical() {
+    let world = default_world();
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::default(),
+        Vec3::new(0., 1., 0.),
+    );
+    let cam = Camera::new(23, 17, FRAC_PI_2)
+        .with_transform(transform)
+        .with_samples(3)
+        .with_filter(Filter::new_mitchell(2.0, 1. / 3., 1. / 3.))
+        .with_tile_size(5);
+
+    let single = cam.clone().render(&world);
+    let multi = cam.with_threads(4).render(&world);
+
+    assert_eq!(single.as_raw(), multi.as_raw());
+}
+
+#[test]
+fn tile_size_does_not_change_render() {
+    let world = default_world();
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::default(),
+        Vec3::new(0., 1., 0.),
+    );
+    let cam = Camera::new(23, 17, FRAC_PI_2)
+        .with_transform(transform)
+        .with_samples(3)
+        .with_filter(Filter::new_mitchell(2.0, 1. / 3., 1. / 3.))
+        .with_threads(3);
+
+    // samples near the borders of the tiles are splatted across them
+    let small = cam.clone().with_tile_size(4).render(&world);
+    let large = cam.with_tile_size(7).render(&world);
+
+    assert_eq!(small.as_raw(), large.as_raw());
+}
+
+fn progressive_camera() -> Camera {
+    let transform = Mat4::view_transform(
+        Point3::new(0., 0., -5.),
+        Point3::default(),
+        Vec3::new(0., 1., 0.),
+    );
+
+    Camera::new(12, 10, FRAC_PI_2)
+        .with_transform(transform)
+        .with_samples(4)
+        .with_filter(Filter::new_tent(1.0))
+        .with_tile_size(4)
+        .with_threads(2)
+}
+
+#[test]
+fn progressive_reports_every_tile_and_pass() {
+    let world = default_world();
+    let cam = progressive_camera();
+    let cancel = AtomicBool::new(false);
+
+    let mut tiles = 0;
+    let mut passes = Vec::new();
+    let result = cam.render_progressive(&world, &cancel, |event| match event {
+        RenderEvent::Tile(_) => tiles += 1,
+        RenderEvent::Pass(progress, image) => {
+            assert_eq!(image.dimensions(), (12, 10));
+            passes.push(progress);
+        }
+    });
+
+    // 3 x 3 tiles in each of the 4 passes
+    assert_eq!(tiles, 36);
+    assert_eq!(passes.len(), 4);
+    assert_eq!(passes[1].pass, 1);
+    assert_relative_eq!(passes[3].fraction(), 1.0);
+    assert_eq!(passes[3].eta, Duration::ZERO);
+
+    assert!(!result.cancelled);
+    assert_eq!(result.passes, 4);
+
+    // the same samples are taken as by a regular render
+    let image = cam.render(&world);
+    for (a, b) in image.pixels().zip(result.image.pixels()) {
+        assert_relative_eq!(Color(*a), Color(*b));
+    }
+}
+
+#[test]
+fn progressive_can_be_cancelled() {
+    let world = default_world();
+    let cam = progressive_camera();
+    let cancel = AtomicBool::new(false);
+
+    let result = cam.render_progressive(&world, &cancel, |event| {
+        if let RenderEvent::Pass(..) = event {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    });
+
+    assert!(result.cancelled);
+    assert_eq!(result.passes, 1);
+    assert_eq!(result.image.dimensions(), (12, 10));
+}
+
+#[test]
+fn cancelled_pass_is_discarded() {
+    let world = default_world();
+    // many tiles, so that the cancellation usually hits a partial pass
+    let cam = Camera::new(64, 48, FRAC_PI_2)
+        .with_transform(progressive_camera().transform().clone())
+        .with_samples(4)
+        .with_tile_size(4)
+        .with_threads(2);
+    let cancel = AtomicBool::new(false);
+
+    let mut passes = Vec::new();
+    let result = cam.render_progressive(&world, &cancel, |event| match event {
+        RenderEvent::Pass(_, image) => passes.push(image.clone()),
+        // stop somewhere in the second pass
+        RenderEvent::Tile(progress) => {
+            if progress.pass == 1 && progress.tiles_done == progress.tiles / 2 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    // the workers may finish the pass before they see the cancellation,
+    // but the image always is the one of the last completed pass
+    assert!(result.cancelled);
+    assert_eq!(result.passes as usize, passes.len());
+    assert_eq!(Some(&result.image), passes.last());
+}
@@ -0,0 +1,61 @@
+use approx::assert_relative_eq;
+
+use crate::filter::Filter;
+
+#[test]
+fn radius() {
+    assert_eq!(Filter::default().radius(), 0.5);
+    assert_eq!(Filter::new_tent(1.5).radius(), 1.5);
+    assert_eq!(Filter::new_lanczos(3.0, 3.0).radius(), 3.0);
+}
+
+#[test]
+fn zero_outside_radius() {
+    let filters = [
+        Filter::new_box(1.0),
+        Filter::new_tent(1.0),
+        Filter::new_gaussian(1.0, 2.0),
+        Filter::new_mitchell(1.0, 1. / 3., 1. / 3.),
+        Filter::new_lanczos(1.0, 1.0),
+    ];
+
+    for f in filters {
+        assert_eq!(f.eval(1.1, 0.0), 0.0);
+        assert_eq!(f.eval(0.0, -1.1), 0.0);
+        assert!(f.eval(0.0, 0.0) > 0.0);
+    }
+}
+
+#[test]
+fn tent_is_separable() {
+    let f = Filter::new_tent(2.0);
+
+    assert_relative_eq!(f.eval(0.0, 0.0), 4.0);
+    assert_relative_eq!(f.eval(1.0, 0.0), 2.0);
+    assert_relative_eq!(f.eval(1.0, 1.0), 1.0);
+}
+
+#[test]
+fn gaussian_reaches_zero_at_radius() {
+    let f = Filter::new_gaussian(1.5, 2.0);
+
+    assert_relative_eq!(f.eval(1.5, 0.0), 0.0);
+    assert!(f.eval(0.5, 0.0) > f.eval(1.0, 0.0));
+}
+
+#[test]
+fn mitchell_has_negative_lobes() {
+    let f = Filter::new_mitchell(2.0, 1. / 3., 1. / 3.);
+
+    assert_relative_eq!(f.eval(0.0, 0.0), (8. / 9.) * (8. / 9.), epsilon = 1e-5);
+    assert!(f.eval(1.5, 0.0) < 0.0);
+}
+
+#[test]
+fn lanczos_zero_crossings() {
+    let f = Filter::new_lanczos(3.0, 3.0);
+
+    assert_relative_eq!(f.eval(0.0, 0.0), 1.0);
+    assert_relative_eq!(f.eval(1.0, 0.0), 0.0);
+    assert_relative_eq!(f.eval(2.0, 0.0), 0.0);
+}
@@ -0,0 +1,110 @@
+use approx::assert_relative_eq;
+
+use crate::{
+    ies::{IesError, IesProfile},
+    lights::PointLight,
+    vec3::{Point3, Vec3},
+};
+
+/// A rotationally symmetric downlight which gets dimmer towards the horizon.
+const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] test
+[MANUFAC] nobody
+TILT=NONE
+1 1000 1.0 4 1 1 2 0.1 0.1 0.0
+1.0 1.0 10
+0 30 60 90
+0
+1000 800 200 0
+";
+
+/// Two horizontal angles with quadrant symmetry, and lamp tilt data.
+const QUADRANT: &str = "IESNA91
+TILT=INCLUDE
+1
+2
+0 90
+1 1
+1 1000 2.0 2 2 1 2 0.1 0.1 0.0
+1.0 1.0 10
+0 90
+0, 90
+100 100
+300 300
+";
+
+#[test]
+fn parse_downlight() {
+    let p = IesProfile::parse(DOWNLIGHT).unwrap();
+
+    assert_relative_eq!(p.max_candela(), 1000.0);
+    assert_relative_eq!(p.candela(0.0, 0.0), 1000.0);
+    assert_relative_eq!(p.candela(45.0, 0.0), 500.0);
+    // the profile is the same all around
+    assert_relative_eq!(p.candela(45.0, 123.0), 500.0);
+    // nothing is emitted above the covered angles
+    assert_relative_eq!(p.candela(120.0, 0.0), 0.0);
+}
+
+#[test]
+fn parse_quadrant_symmetry_and_tilt() {
+    let p = IesProfile::parse(QUADRANT).unwrap();
+
+    // the multiplier is applied
+    assert_relative_eq!(p.max_candela(), 600.0);
+    assert_relative_eq!(p.candela(0.0, 0.0), 200.0);
+    assert_relative_eq!(p.candela(0.0, 45.0), 400.0);
+    assert_relative_eq!(p.candela(0.0, 90.0), 600.0);
+    // mirrored into the other quadrants
+    assert_relative_eq!(p.candela(0.0, 135.0), 400.0);
+    assert_relative_eq!(p.candela(0.0, 270.0), 600.0);
+    assert_relative_eq!(p.candela(0.0, 315.0), 400.0);
+}
+
+#[test]
+fn parse_errors() {
+    assert!(matches!(
+        IesProfile::parse("IESNA91\n1 2 3"),
+        Err(IesError::MissingTilt)
+    ));
+    assert!(matches!(
+        IesProfile::parse("TILT=NONE\n1 1000 1.0 4"),
+        Err(IesError::UnexpectedEnd)
+    ));
+    assert!(matches!(
+        IesProfile::parse("TILT=NONE\n1 1000 x"),
+        Err(IesError::InvalidNumber(_))
+    ));
+    assert!(matches!(
+        IesProfile::parse(&DOWNLIGHT.replace("0 30 60 90", "0 60 30 90")),
+        Err(IesError::InvalidAngles)
+    ));
+    assert!(matches!(
+        IesProfile::parse(&DOWNLIGHT.replace("4 1 1 2", "1e30 1e30 1 2")),
+        Err(IesError::InvalidAngles)
+    ));
+    // type B photometry
+    assert!(matches!(
+        IesProfile::parse(&DOWNLIGHT.replace("4 1 1 2", "4 1 2 2")),
+        Err(IesError::UnsupportedPhotometry(t)) if t == 2.0
+    ));
+}
+
+#[test]
+fn profile_modulates_point_light() {
+    let p = IesProfile::parse(DOWNLIGHT).unwrap();
+    let light = PointLight::new((0., 10., 0.), [1., 1., 1.]).with_profile(p);
+
+    // straight below the light
+    let below = light.sample(Point3::new(0., 0., 0.));
+    assert_relative_eq!(below.intensity, [1., 1., 1.].into());
+
+    // 60 degrees away from the nadir
+    let dir = Vec3::new(60f32.to_radians().sin(), -60f32.to_radians().cos(), 0.);
+    let side = light.sample(Point3::new(0., 10., 0.) + dir * 5.0);
+    assert_relative_eq!(side.intensity, [0.2, 0.2, 0.2].into());
+
+    // above the light
+    let above = light.sample(Point3::new(0., 20., 0.));
+    assert_relative_eq!(above.intensity, [0., 0., 0.].into());
+}
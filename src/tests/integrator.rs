@@ -0,0 +1,188 @@
+use std::f32::consts::PI;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    integrator::{AmbientOcclusion, Integrator, PathTracer},
+    lights::{AreaLight, PointLight},
+    material::Material,
+    matrix::Mat4,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    world::World,
+    Color,
+};
+
+fn lambertian(color: Color) -> Material {
+    Material::default()
+        .with_color(color)
+        .with_diffuse(1.0)
+        .with_specular(0.0)
+}
+
+fn average(w: &World, r: &Ray, samples: u32) -> Color {
+    let total = (0..samples).fold(Color::BLACK, |acc, i| {
+        acc + w.color_at_sampled(r, &mut Sampler::new(u64::from(i)))
+    });
+    total * (1.0 / samples as f32)
+}
+
+#[test]
+fn no_light_is_black() {
+    let s = Sphere::new(lambertian([1., 1., 1.].into()));
+    let w = World::new(vec![s], Vec::<PointLight>::new())
+        .with_integrator(Integrator::PathTracer(PathTracer::new(8)));
+
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    assert_relative_eq!(average(&w, &r, 16), Color::BLACK);
+}
+
+#[test]
+fn single_bounce_is_direct_lighting() {
+    let s = Sphere::new(lambertian([1., 1., 1.].into()));
+    let light = PointLight::new((0., 0., -10.), [1., 1., 1.]);
+    let w = World::new(vec![s], vec![light])
+        .with_integrator(Integrator::PathTracer(PathTracer::new(1)));
+
+    // the light is straight above the hit point, no ambient term is added
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let c = 1.0 / PI;
+    assert_relative_eq!(
+        w.color_at_sampled(&r, &mut Sampler::default()),
+        [c, c, c].into(),
+        epsilon = 1e-5
+    );
+}
+
+#[test]
+fn indirect_light_bleeds_colour() {
+    // the underside of the ball only sees the red floor, never the light
+    let ball = Sphere::new(lambertian([1., 1., 1.].into()));
+    let floor = Sphere::new(lambertian([0.8, 0.1, 0.1].into())).with_transform(
+        Mat4::new_translation((0., -2., 0.).into()) * &Mat4::new_scaling((20., 0.01, 20.).into()),
+    );
+    let light = PointLight::new((6., 6., 0.), [1., 1., 1.]);
+    let r = Ray::new((0., -0.9, -5.), (0., 0., 1.));
+
+    let direct = World::new(vec![ball.clone(), floor.clone()], vec![light.clone()])
+        .with_integrator(Integrator::PathTracer(PathTracer::new(1)));
+    assert_relative_eq!(average(&direct, &r, 16), Color::BLACK);
+
+    let global = World::new(vec![ball, floor], vec![light])
+        .with_integrator(Integrator::PathTracer(PathTracer::new(4)));
+    let image::Rgb([red, green, blue]) = average(&global, &r, 256).into_inner();
+    assert!(green > 0.0);
+    assert!(red > 4.0 * green && red > 4.0 * blue);
+}
+
+#[test]
+fn small_and_large_area_lights_converge() {
+    // a white floor below a disk light. With `L = I / A`, the floor
+    // reflects `I / (π (h^2 + R^2))`.
+    let floor = Sphere::new(lambertian([1., 1., 1.].into()))
+        .with_transform(Mat4::new_scaling((100., 0.01, 100.).into()));
+    let r = Ray::new((0., 0.5, 0.), (0., -1., 0.));
+
+    for radius in [0.1, 3.0] {
+        let light = AreaLight::new_disk((0., 1.01, 0.), (0., -1., 0.), radius, [1., 1., 1.]);
+        let w = World::new(vec![floor.clone()], vec![light])
+            .with_integrator(Integrator::PathTracer(PathTracer::new(1)));
+
+        let expected = 1.0 / (PI * (1.0 + radius * radius));
+        let c = average(&w, &r, 64).into_inner()[0];
+        assert_relative_eq!(c, expected, max_relative = 0.05);
+    }
+}
+
+#[test]
+fn integrators_agree_on_area_lights() {
+    let floor = Sphere::new(lambertian([1., 1., 1.].into()).with_ambient(0.0))
+        .with_transform(Mat4::new_scaling((100., 0.01, 100.).into()));
+    let light = AreaLight::new_disk((0., 1.01, 0.), (0., -1., 0.), 1.0, [1., 1., 1.]);
+    let r = Ray::new((0., 0.5, 0.), (0., -1., 0.));
+
+    let whitted = World::new(vec![floor.clone()], vec![light.clone()]);
+    let path_traced = World::new(vec![floor], vec![light])
+        .with_integrator(Integrator::PathTracer(PathTracer::new(1)));
+
+    // the Whitted integrator scales the BRDF by `π`, as for point lights
+    let expected = average(&path_traced, &r, 64) * PI;
+    assert_relative_eq!(average(&whitted, &r, 64), expected, max_relative = 0.05);
+}
+
+#[test]
+fn area_lights_are_visible() {
+    let light = AreaLight::new_rect((-1., -1., 2.), (2., 0., 0.), (0., 2., 0.), [4., 4., 4.]);
+    let w = World::new(Vec::new(), vec![light])
+        .with_integrator(Integrator::PathTracer(PathTracer::new(4)));
+
+    let c = w.color_at_sampled(
+        &Ray::new((0.5, 0., 0.), (0., 0., 1.)),
+        &mut Sampler::default(),
+    );
+    assert_relative_eq!(c, [1., 1., 1.].into());
+}
+
+#[test]
+fn emissive_sphere_lights_floor() {
+    // a glowing ball of radius `R` at height `h` above a white floor,
+    // which reflects `L R^2 / h^2`.
+    let floor = Sphere::new(lambertian([1., 1., 1.].into()))
+        .with_transform(Mat4::new_scaling((100., 0.01, 100.).into()));
+    let ball = Sphere::new(lambertian(Color::BLACK).with_emission([1., 1., 1.].into(), 4.0))
+        .with_transform(Mat4::new_scaling((0.5, 0.5, 0.5).into()).translate((0., 2.01, 0.).into()));
+    let w = World::new(vec![floor, ball], Vec::<PointLight>::new())
+        .with_integrator(Integrator::PathTracer(PathTracer::new(1)));
+
+    let r = Ray::new((0., 1., 0.), (0., -1., 0.));
+    let c = average(&w, &r, 64).into_inner()[0];
+    assert_relative_eq!(c, 0.25, max_relative = 0.05);
+
+    // the ball itself is seen with its emission
+    let r = Ray::new((0., 2.01, -5.), (0., 0., 1.));
+    assert_relative_eq!(average(&w, &r, 4), [4., 4., 4.].into());
+}
+
+/// A ball resting on a large floor.
+fn ball_on_floor() -> World {
+    let floor = Sphere::new(lambertian([1., 1., 1.].into())).with_transform(
+        Mat4::new_scaling((1000., 1000., 1000.).into()).translate((0., -1000., 0.).into()),
+    );
+    let ball = Sphere::new(lambertian([1., 1., 1.].into()))
+        .with_transform(Mat4::new_translation((0., 1., 0.).into()));
+    let light = PointLight::new((0., 10., -10.), [1., 1., 1.]);
+
+    World::new(vec![floor, ball], vec![light])
+}
+
+#[test]
+fn ambient_occlusion_render() {
+    let ao = AmbientOcclusion::new(256, 2.0);
+    let w = ball_on_floor().with_integrator(Integrator::AmbientOcclusion(ao));
+
+    let open = Ray::new((10., 5., 0.), (0., -1., 0.));
+    assert_relative_eq!(average(&w, &open, 1), Color::WHITE);
+
+    let contact = Ray::new((0.5, 1., -5.), (0., -1., 5.));
+    let c = average(&w, &contact, 1).into_inner()[0];
+    assert!(c > 0.0 && c < 0.75, "{c}");
+
+    let missed = Ray::new((0., 5., 0.), (0., 1., 0.));
+    assert_relative_eq!(average(&w, &missed, 1), Color::BLACK);
+}
+
+#[test]
+fn ambient_occlusion_darkens_ambient_term() {
+    let plain = ball_on_floor();
+    let w = ball_on_floor().with_ambient_occlusion(AmbientOcclusion::new(256, 2.0));
+
+    let open = Ray::new((10., 5., 0.), (0., -1., 0.));
+    assert_relative_eq!(average(&w, &open, 1), average(&plain, &open, 1));
+
+    // only the ambient term is darkened
+    let contact = Ray::new((0.5, 1., -5.), (0., -1., 5.));
+    let darkened = average(&plain, &contact, 1) - average(&w, &contact, 1);
+    let d = darkened.into_inner()[0];
+    assert!(d > 0.025 && d < 0.1, "{d}");
+}
@@ -0,0 +1,166 @@
+use std::f32::consts::{FRAC_PI_4, FRAC_PI_6, PI};
+
+use approx::assert_relative_eq;
+
+use crate::{
+    lights::{Attenuation, DirectionalLight, Light, PointLight, Power, SpotLight},
+    sampler::Sampler,
+    vec3::{Point3, Vec3},
+    Color,
+};
+
+fn spot() -> SpotLight {
+    SpotLight::new(
+        (0., 10., 0.),
+        (0., -1., 0.),
+        FRAC_PI_6,
+        FRAC_PI_4,
+        [1., 1., 1.],
+    )
+}
+
+#[test]
+fn spot_falloff() {
+    let light = spot();
+
+    assert_relative_eq!(light.falloff(Vec3::new(0., -1., 0.)), 1.0);
+    // exactly on the edge of the outer cone
+    let edge = Vec3::new(FRAC_PI_4.sin(), -FRAC_PI_4.cos(), 0.);
+    assert_relative_eq!(light.falloff(edge), 0.0, epsilon = 1e-4);
+    // pointing away from the light
+    assert_relative_eq!(light.falloff(Vec3::new(0., 1., 0.)), 0.0);
+
+    // between the cones the falloff decreases monotonically
+    let angles = [0.55, 0.6, 0.65, 0.7, 0.75];
+    let falloff: Vec<_> = angles
+        .iter()
+        .map(|a: &f32| light.falloff(Vec3::new(a.sin(), -a.cos(), 0.)))
+        .collect();
+    assert!(falloff.windows(2).all(|w| w[0] > w[1]));
+    assert!(falloff[0] < 1.0 && falloff[4] > 0.0);
+}
+
+#[test]
+fn spot_samples() {
+    let light: Light = spot().into();
+    let mut sampler = Sampler::default();
+
+    let below = light.samples(Point3::new(0., 0., 0.), &mut sampler);
+    assert_eq!(below.len(), 1);
+    assert_relative_eq!(below[0].lightv, Vec3::new(0., 1., 0.));
+    assert_relative_eq!(below[0].distance, 10.0);
+    assert_relative_eq!(below[0].intensity, [1., 1., 1.].into());
+
+    let outside = light.samples(Point3::new(20., 0., 0.), &mut sampler);
+    assert_relative_eq!(outside[0].intensity, [0., 0., 0.].into());
+}
+
+#[test]
+fn directional_samples_are_parallel() {
+    let light: Light = DirectionalLight::new((1., -1., 0.), [1., 0.9, 0.8]).into();
+    let mut sampler = Sampler::default();
+
+    for point in [Point3::new(0., 0., 0.), Point3::new(-100., 3., 50.)] {
+        let samples = light.samples(point, &mut sampler);
+
+        assert_eq!(samples.len(), 1);
+        assert_relative_eq!(samples[0].lightv, Vec3::new(-1., 1., 0.).normalize());
+        assert!(samples[0].distance.is_infinite());
+        assert_relative_eq!(samples[0].intensity, [1., 0.9, 0.8].into());
+    }
+}
+
+#[test]
+fn attenuation_factor() {
+    assert_relative_eq!(Attenuation::None.factor(10.0), 1.0);
+    assert_relative_eq!(Attenuation::InverseSquare.factor(2.0), 0.25);
+
+    let poly = Attenuation::Polynomial {
+        constant: 1.0,
+        linear: 0.5,
+        quadratic: 0.25,
+    };
+    assert_relative_eq!(poly.factor(2.0), 1.0 / 3.0);
+}
+
+#[test]
+fn attenuated_point_light() {
+    let light =
+        PointLight::new((0., 4., 0.), [1., 1., 1.]).with_attenuation(Attenuation::InverseSquare);
+
+    let sample = light.sample(Point3::default());
+    assert_relative_eq!(sample.intensity, [0.0625, 0.0625, 0.0625].into());
+}
+
+#[test]
+fn power_units() {
+    assert_relative_eq!(Power::Watts(10.0).watts(), 10.0);
+    assert_relative_eq!(Power::Lumens(683.0).watts(), 1.0);
+
+    // a white point light spreads its power over the whole sphere
+    let light = PointLight::new((0., 0., 0.), [1., 1., 1.]).with_power(Power::Watts(4.0 * PI));
+    assert_relative_eq!(light.intensity, [1., 1., 1.].into());
+
+    // the colour is kept, scaled to unit luminance
+    let light = PointLight::new((0., 0., 0.), [1., 0.5, 0.]).with_power(Power::Lumens(683.0));
+    let intensity = light.intensity;
+    assert_relative_eq!(intensity.luminance() * 4.0 * PI, 1.0);
+    assert_relative_eq!(intensity.into_inner()[1] / intensity.into_inner()[0], 0.5);
+}
+
+#[test]
+fn spot_power_is_concentrated_in_cone() {
+    let power = Power::Watts(100.0);
+    let point = PointLight::new((0., 0., 0.), [1., 1., 1.]).with_power(power);
+    let spot = spot().with_power(power);
+
+    assert!(spot.intensity.luminance() > point.intensity.luminance());
+}
+
+#[test]
+fn temperature_keeps_power() {
+    let power = Power::Lumens(800.0);
+    let white = PointLight::new((0., 0., 0.), [1., 1., 1.]).with_power(power);
+    let warm = Light::from(white.clone()).with_temperature(2700.0);
+
+    assert_relative_eq!(
+        warm.intensity().luminance(),
+        white.intensity.luminance(),
+        max_relative = 1e-5
+    );
+    assert_relative_eq!(
+        warm.intensity(),
+        Color::from_temperature(2700.0) * (power.watts() / (4.0 * PI)),
+        max_relative = 1e-3
+    );
+
+    // warm light is reddish
+    let [r, _, b] = warm.intensity().into_inner().0;
+    assert!(r > b);
+}
+
+#[test]
+fn ambient_falls_off_with_distance() {
+    let light: Light = PointLight::new((0., 0., 0.), [1., 1., 1.])
+        .with_power(Power::Watts(4.0 * PI))
+        .with_attenuation(Attenuation::InverseSquare)
+        .into();
+
+    assert_relative_eq!(
+        light.ambient(Point3::new(0., 2., 0.)),
+        [0.25, 0.25, 0.25].into()
+    );
+    assert_relative_eq!(
+        light.ambient(Point3::new(0., 4., 0.)),
+        [0.0625, 0.0625, 0.0625].into()
+    );
+
+    // the cone only limits the direct light
+    let light: Light = spot().with_attenuation(Attenuation::InverseSquare).into();
+    let outside = Point3::new(20., 10., 0.);
+    assert_relative_eq!(
+        light.samples(outside, &mut Sampler::default())[0].intensity,
+        Color::BLACK
+    );
+    assert_relative_eq!(light.ambient(outside), [0.0025, 0.0025, 0.0025].into());
+}
@@ -1,33 +1,62 @@
-use std::f32::consts::FRAC_1_SQRT_2;
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
 
 use approx::assert_relative_eq;
 
 use crate::{
+    bsdf::Bsdf,
+    hit_list::{HitRec, HitState},
     lights::PointLight,
     material::Material,
+    pbr::PbrMaterial,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
     vec3::{Point3, Vec3},
+    Color,
 };
 
-fn get_defaults() -> (Material, Point3) {
-    (Material::default(), Point3::default())
+fn get_defaults() -> (Sphere, Point3) {
+    (Sphere::default(), Point3::default())
+}
+
+/// Returns the state of a hit at `pos` on the object, seen along `eyev`.
+fn hit_at(obj: &Sphere, pos: Point3, eyev: Vec3, normal: Vec3) -> HitState<'_> {
+    HitState {
+        t: 0.0,
+        obj,
+        point: pos,
+        over_point: pos,
+        under_point: pos,
+        eyev,
+        normal,
+        tangent: normal.basis().0,
+        inside: false,
+        n1: 1.0,
+        n2: 1.0,
+        within: None,
+    }
 }
 
 #[test]
 fn eye_between_light_and_surface() {
-    let (m, pos) = get_defaults();
+    let (obj, pos) = get_defaults();
     let eyev = Vec3::new(0., 0., -1.);
     let normal = Vec3::new(0., 0., -1.);
     let light = PointLight::new((0., 0., -10.), [1., 1., 1.]);
 
     // all kinds of reflection at full strength so
     // color = ambient + diffuse + specular.
-    let color = m.lighting(&light, pos, eyev, normal);
+    let color = obj
+        .material()
+        .lighting(&light, &hit_at(&obj, pos, eyev, normal));
     assert_relative_eq!(color, [1.9, 1.9, 1.9].into());
 
     // eye is offset by 45 degrees.
     let eyev = Vec3::new(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
     // here specular is effectively zero.
-    let color = m.lighting(&light, pos, eyev, normal);
+    let color = obj
+        .material()
+        .lighting(&light, &hit_at(&obj, pos, eyev, normal));
     assert_relative_eq!(color, [1.0, 1.0, 1.0].into());
 }
 
@@ -35,38 +64,172 @@ fn eye_between_light_and_surface() {
 fn eye_opposite_surface() {
     // here, the light is offset by 45 degrees.
 
-    let (m, pos) = get_defaults();
+    let (obj, pos) = get_defaults();
     let eyev = Vec3::new(0., 0., -1.);
     let normal = Vec3::new(0., 0., -1.);
     let light = PointLight::new((0., 10., -10.), [1., 1., 1.]);
 
     // specular = 0, diffuse = 0.9 * 1/(2.sqrt())
-    let color = m.lighting(&light, pos, eyev, normal);
+    let color = obj
+        .material()
+        .lighting(&light, &hit_at(&obj, pos, eyev, normal));
     let i = 0.7364;
     assert_relative_eq!(color, [i, i, i].into());
 }
 
 #[test]
 fn eye_and_light_offset_by_45() {
-    let (m, pos) = get_defaults();
+    let (obj, pos) = get_defaults();
     let eyev = Vec3::new(0., -FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
     let normal = Vec3::new(0., 0., -1.);
     let light = PointLight::new((0., 10., -10.), [1., 1., 1.]);
 
     // specular = 0.9, diffuse = 0.9 * 1/(2.sqrt())
-    let color = m.lighting(&light, pos, eyev, normal);
+    let color = obj
+        .material()
+        .lighting(&light, &hit_at(&obj, pos, eyev, normal));
     let i = 1.6364;
     assert_relative_eq!(color, [i, i, i].into());
 }
 
 #[test]
 fn light_behind_surface() {
-    let (m, pos) = get_defaults();
+    let (obj, pos) = get_defaults();
     let eyev = Vec3::new(0., 0., -1.);
     let normal = Vec3::new(0., 0., -1.);
     let light = PointLight::new((0., 0., 10.), [1., 1., 1.]);
 
     // only ambient since light is behind the surface.
-    let color = m.lighting(&light, pos, eyev, normal);
+    let color = obj
+        .material()
+        .lighting(&light, &hit_at(&obj, pos, eyev, normal));
     assert_relative_eq!(color, [0.1, 0.1, 0.1].into());
 }
+
+#[test]
+fn brdf_samples_match_pdf() {
+    // hit the front of a unit sphere, facing the eye
+    let sphere = Sphere::default();
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let state = HitRec {
+        t: 4.0,
+        obj: &sphere,
+    }
+    .prepare_computations(&r);
+    let (eyev, normal) = (state.eyev, state.normal);
+    let mut sampler = Sampler::default();
+
+    // for a Lambertian surface, every sample estimates the albedo exactly
+    let m = Material::default().with_diffuse(0.8).with_specular(0.0);
+    for _ in 0..32 {
+        let s = m.sample(&state, eyev, &mut sampler).unwrap();
+        assert_relative_eq!(s.pdf, m.pdf(&state, s.lightv, eyev));
+        let estimate = s.value * (s.lightv.dot(normal) / s.pdf);
+        assert_relative_eq!(estimate, [0.8, 0.8, 0.8].into(), epsilon = 1e-4);
+    }
+
+    // the specular lobe sends most samples close to the mirror direction
+    let m = Material::default().with_diffuse(0.0).with_specular(1.0);
+    let near_mirror = (0..64)
+        .filter_map(|_| m.sample(&state, eyev, &mut sampler))
+        .filter(|s| s.lightv.dot(normal) > 0.9)
+        .count();
+    assert!(near_mirror > 56);
+}
+
+/// A two-tone cartoon shader, which only implements `eval()`.
+#[derive(Debug)]
+struct Toon {
+    lit: Color,
+    shadow: Color,
+}
+
+impl Bsdf for Toon {
+    fn eval(&self, state: &HitState<'_>, lightv: Vec3, _eyev: Vec3) -> Color {
+        let cos = lightv.dot(state.normal);
+        let color = if cos > 0.5 { self.lit } else { self.shadow };
+
+        // cancel the cosine so that the bands stay flat
+        color * (1.0 / (PI * cos.max(1e-3)))
+    }
+}
+
+#[test]
+fn custom_bsdf() {
+    let toon = Toon {
+        lit: [1., 0.5, 0.].into(),
+        shadow: [0.2, 0.1, 0.].into(),
+    };
+    let m = Material::default().with_ambient(0.0).with_bsdf(toon);
+    let sphere = Sphere::new(m);
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let state = HitRec {
+        t: 4.0,
+        obj: &sphere,
+    }
+    .prepare_computations(&r);
+    let m = sphere.material();
+
+    let head_on = PointLight::new((0., 0., -10.), [1., 1., 1.]).sample(state.point);
+    let grazing = PointLight::new((-10., 0., -2.), [1., 1., 1.]).sample(state.point);
+    assert_relative_eq!(
+        m.lighting_samples(head_on.intensity, &[head_on], &state),
+        [1., 0.5, 0.].into(),
+        epsilon = 1e-5
+    );
+    assert_relative_eq!(
+        m.lighting_samples(grazing.intensity, &[grazing], &state),
+        [0.2, 0.1, 0.].into(),
+        epsilon = 1e-5
+    );
+
+    // lighting() uses the BSDF as well
+    let light = PointLight::new((0., 0., -10.), [1., 1., 1.]);
+    assert_relative_eq!(
+        m.lighting(&light, &state),
+        [1., 0.5, 0.].into(),
+        epsilon = 1e-5
+    );
+
+    // sampling falls back to the cosine weighted hemisphere
+    let s = m
+        .sample(&state, state.eyev, &mut Sampler::default())
+        .unwrap();
+    assert_relative_eq!(s.pdf, s.lightv.dot(state.normal) / PI);
+}
+
+#[test]
+fn absorption_follows_beer_lambert() {
+    let m = Material::default().with_absorption([0.5, 0.25, 1.0].into(), 2.0);
+
+    assert_relative_eq!(m.attenuation(0.0), [1., 1., 1.].into());
+    assert_relative_eq!(m.attenuation(2.0), [0.5, 0.25, 1.0].into());
+    assert_relative_eq!(m.attenuation(4.0), [0.25, 0.0625, 1.0].into());
+}
+
+#[test]
+fn materials_compare_their_bsdfs() {
+    let pbr = || PbrMaterial::new([0.9, 0.6, 0.1].into(), 1.0, 0.3);
+    let a = Material::default().with_pbr(pbr());
+
+    assert_eq!(a, Material::default().with_pbr(pbr()));
+    assert_ne!(
+        a,
+        Material::default().with_pbr(pbr().with_clearcoat(1.0, 0.1))
+    );
+    assert_ne!(a, a.clone().with_bsdf(Material::default()));
+
+    // models without PartialEq are only equal to themselves
+    let toon = Material::default().with_bsdf(Toon {
+        lit: Color::WHITE,
+        shadow: Color::BLACK,
+    });
+    assert_eq!(toon, toon.clone());
+    assert_ne!(
+        toon,
+        Material::default().with_bsdf(Toon {
+            lit: Color::WHITE,
+            shadow: Color::BLACK,
+        })
+    );
+}
@@ -0,0 +1,163 @@
+use std::f32::consts::{FRAC_PI_6, PI};
+
+use approx::assert_relative_eq;
+
+use crate::{
+    integrator::{Integrator, PathTracer},
+    lights::{DirectionalLight, LightSample, PointLight, SpotLight},
+    material::Material,
+    matrix::Mat4,
+    medium::Medium,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+#[test]
+fn beer_lambert() {
+    let m = Medium::new([0.1, 0.2, 0.].into(), [0.1, 0., 0.].into(), 0.0);
+
+    assert_relative_eq!(m.sigma_t(), [0.2, 0.2, 0.].into());
+    assert_relative_eq!(
+        m.transmittance(5.0),
+        [(-1.0f32).exp(), (-1.0f32).exp(), 1.0].into()
+    );
+}
+
+#[test]
+fn henyey_greenstein() {
+    let eyev = Vec3::new(0., 0., 1.);
+    let mut sampler = Sampler::new(1);
+
+    for g in [-0.5, 0.0, 0.8] {
+        let m = Medium::new(Color::BLACK, [1., 1., 1.].into(), g);
+
+        // the mean cosine of the scattering angle is `g`
+        let n = 20_000;
+        let mean = (0..n)
+            .map(|_| -m.sample_phase(eyev, &mut sampler).dot(eyev))
+            .sum::<f32>()
+            / n as f32;
+        assert_relative_eq!(mean, g, epsilon = 0.02);
+
+        // the phase function integrates to one over the sphere
+        let total = (0..n)
+            .map(|_| {
+                let (u, v) = sampler.next_2d();
+                let z = 1.0 - 2.0 * u;
+                let r = (1.0 - z * z).sqrt();
+                let lightv = Vec3::new(r * (2.0 * PI * v).cos(), r * (2.0 * PI * v).sin(), z);
+                m.phase(lightv, eyev) * 4.0 * PI
+            })
+            .sum::<f32>()
+            / n as f32;
+        assert_relative_eq!(total, 1.0, epsilon = 0.05);
+    }
+}
+
+/// A glowing ball behind an absorbing fog or a ball of absorbing medium.
+fn glow_behind(fog: Option<Medium>, blob: Option<Medium>) -> World {
+    let glow = Sphere::new(
+        Material::default()
+            .with_ambient(0.0)
+            .with_emission([1., 1., 1.].into(), 1.0),
+    )
+    .with_transform(Mat4::new_translation((0., 0., 10.).into()));
+
+    let mut objects = vec![glow];
+    if let Some(blob) = blob {
+        objects.push(Sphere::default().with_medium(blob));
+    }
+
+    let world = World::new(objects, Vec::<PointLight>::new());
+    match fog {
+        Some(fog) => world.with_fog(fog),
+        None => world,
+    }
+}
+
+#[test]
+fn fog_absorbs_light() {
+    let fog = Medium::new([0.1, 0.1, 0.1].into(), Color::BLACK, 0.0);
+    let w = glow_behind(Some(fog), None);
+
+    // the glowing ball is 14 units away from the eye
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let expected = (-1.4f32).exp();
+    assert_relative_eq!(w.color_at(&r), [expected, expected, expected].into());
+}
+
+#[test]
+fn media_inside_shapes() {
+    let blob = Medium::new([0.5, 0.5, 0.5].into(), Color::BLACK, 0.0);
+    let w = glow_behind(None, Some(blob.clone()));
+
+    // the boundary of the medium does not cast shadows, but dims the light
+    let sample = LightSample::towards(
+        Point3::new(0., 0., -5.),
+        Point3::new(0., 0., 5.),
+        [1., 1., 1.].into(),
+    );
+    assert!(!w.is_shadowed(Point3::new(0., 0., -5.), &sample));
+    assert_relative_eq!(
+        w.transmittance(Point3::new(0., 0., -5.), &sample, &mut Sampler::default()),
+        [(-1.0f32).exp(), (-1.0f32).exp(), (-1.0f32).exp()].into()
+    );
+
+    // the path tracer agrees with ray marching on average
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let expected = (-1.0f32).exp();
+    assert_relative_eq!(w.color_at(&r), [expected, expected, expected].into());
+
+    let w =
+        glow_behind(None, Some(blob)).with_integrator(Integrator::PathTracer(PathTracer::new(4)));
+    let n = 512;
+    let total = (0..n).fold(Color::BLACK, |acc, i| {
+        acc + w.color_at_sampled(&r, &mut Sampler::new(i))
+    });
+    let c = (total * (1.0 / n as f32)).into_inner()[0];
+    assert_relative_eq!(c, expected, epsilon = 0.05);
+}
+
+#[test]
+fn light_shafts() {
+    let spot = SpotLight::new(
+        (0., 5., 0.),
+        (0., -1., 0.),
+        FRAC_PI_6 / 2.0,
+        FRAC_PI_6,
+        [1., 1., 1.],
+    );
+    let w = World::new(Vec::new(), vec![spot]).with_fog(Medium::new_fog(0.1));
+
+    // rays crossing the cone of the light see it scattered by the fog
+    let through = w.color_at(&Ray::new((-5., 0., 0.), (1., 0., 0.)));
+    let beside = w.color_at(&Ray::new((-5., 0., 5.), (1., 0., 0.)));
+
+    assert!(through.into_inner()[0] > 0.0);
+    assert_relative_eq!(beside, Color::BLACK);
+}
+
+#[test]
+fn sunlight_through_ground_fog() {
+    let floor = Sphere::new(Material::default().with_ambient(0.0))
+        .with_transform(Mat4::new_scaling((10., 0.01, 10.).into()));
+    let sun = DirectionalLight::new((0., -1., 0.), [1., 1., 1.]);
+    let clear = World::new(vec![floor], vec![sun]);
+    let r = Ray::new((0., 0.5, 0.), (0., -1., 0.));
+    let lit = clear.color_at(&r).into_inner()[0];
+
+    // the sun is infinitely far away, so unbounded fog blocks it entirely
+    let w = World::new(clear.objects.clone(), clear.lights.clone());
+    let foggy = w.with_fog(Medium::new_fog(0.2)).color_at(&r).into_inner()[0];
+    assert!(foggy < 1e-3 * lit);
+
+    // a layer of ground fog only dims it
+    let w = clear.with_fog(Medium::new_fog(0.2)).with_fog_height(1.0);
+    let dimmed = w.color_at(&r).into_inner()[0];
+    assert!(dimmed > 0.5 * lit);
+    assert!(dimmed < lit);
+}
@@ -0,0 +1,44 @@
+use std::f32::consts::PI;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    bsdf::Bsdf, hit_list::HitRec, oren_nayar::OrenNayar, ray::Ray, sphere::Sphere, vec3::Vec3,
+};
+
+#[test]
+fn smooth_is_lambertian() {
+    let sphere = Sphere::default();
+    let r = Ray::new((0., 5., 0.), (0., -1., 0.));
+    let state = HitRec {
+        t: 4.0,
+        obj: &sphere,
+    }
+    .prepare_computations(&r);
+
+    let m = OrenNayar::new([0.5, 0.5, 0.5].into(), 0.0);
+    let f = m.eval(&state, Vec3::new(0.6, 0.8, 0.), Vec3::new(0., 0.6, 0.8));
+    assert_relative_eq!(f, [0.5 / PI, 0.5 / PI, 0.5 / PI].into());
+}
+
+#[test]
+fn rough_surfaces_reflect_back() {
+    let sphere = Sphere::default();
+    let r = Ray::new((0., 5., 0.), (0., -1., 0.));
+    let state = HitRec {
+        t: 4.0,
+        obj: &sphere,
+    }
+    .prepare_computations(&r);
+
+    let m = OrenNayar::new([1., 1., 1.].into(), 0.5);
+    let lightv = Vec3::new(0.8, 0.6, 0.);
+
+    // looking from the light is brighter than looking from the other side
+    let back = m.eval(&state, lightv, lightv).into_inner()[0];
+    let away = m
+        .eval(&state, lightv, Vec3::new(-0.8, 0.6, 0.))
+        .into_inner()[0];
+    assert!(back > away);
+    assert!(away < 1.0 / PI);
+}
@@ -0,0 +1,85 @@
+use std::io::Cursor;
+
+use approx::assert_relative_eq;
+use image::{codecs::hdr::HdrDecoder, Rgb, Rgb32FImage};
+
+use crate::output::{self, Channel};
+
+fn gradient() -> Rgb32FImage {
+    Rgb32FImage::from_fn(3, 2, |x, y| Rgb([x as f32 * 4.0, y as f32 + 0.5, 0.25]))
+}
+
+fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn pfm_stores_rows_from_the_bottom() {
+    let mut bytes = Vec::new();
+    output::write_pfm(&mut bytes, &gradient()).unwrap();
+
+    let header = b"PF\n3 2\n-1.0\n";
+    assert!(bytes.starts_with(header));
+    assert_eq!(bytes.len(), header.len() + 3 * 2 * 3 * 4);
+
+    // the first pixel in the file is the bottom left one
+    let data = header.len();
+    assert_eq!(f32_at(&bytes, data + 4), 1.5);
+    // the last pixel is the top right one
+    assert_eq!(f32_at(&bytes, data + 5 * 12), 8.0);
+    assert_eq!(f32_at(&bytes, data + 5 * 12 + 4), 0.5);
+}
+
+#[test]
+fn hdr_keeps_values_above_one() {
+    let dir = std::env::temp_dir().join("raytracer-output-test.hdr");
+    output::save_image(&gradient(), &dir).unwrap();
+
+    let bytes = std::fs::read(&dir).unwrap();
+    std::fs::remove_file(&dir).unwrap();
+    let pixels = HdrDecoder::new(Cursor::new(bytes))
+        .unwrap()
+        .read_image_hdr()
+        .unwrap();
+
+    assert_eq!(pixels.len(), 6);
+    for (pixel, expected) in pixels.iter().zip(gradient().pixels()) {
+        for c in 0..3 {
+            assert_relative_eq!(pixel.0[c], expected.0[c], max_relative = 0.01);
+        }
+    }
+}
+
+#[test]
+fn exr_layout() {
+    let channels = [
+        Channel::new("depth.Z", vec![1.0, 2.0]),
+        Channel::new("B", vec![3.0, 4.0]),
+    ];
+    let mut bytes = Vec::new();
+    output::write_exr(&mut bytes, 1, 2, &channels).unwrap();
+
+    assert_eq!(&bytes[..4], &[0x76, 0x2f, 0x31, 0x01]);
+    assert_eq!(&bytes[4..8], &[2, 0, 0, 0]);
+
+    let find = |s: &[u8]| bytes.windows(s.len()).position(|w| w == s).unwrap();
+
+    // the channels are sorted by name
+    assert!(find(b"B\0") < find(b"depth.Z\0"));
+
+    // the offset table follows the header and points to the scanlines
+    let header_end = find(b"screenWindowWidth\0float\0") + 33;
+    let offset = |y: usize| {
+        let at = header_end + 8 * y;
+        u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize
+    };
+    assert_eq!(offset(0), header_end + 16);
+    assert_eq!(offset(1), offset(0) + 16);
+    assert_eq!(bytes.len(), offset(1) + 16);
+
+    // every scanline has its row, size and the values channel by channel
+    let line = offset(1);
+    assert_eq!(&bytes[line..line + 8], &[1, 0, 0, 0, 8, 0, 0, 0]);
+    assert_eq!(f32_at(&bytes, line + 8), 4.0);
+    assert_eq!(f32_at(&bytes, line + 12), 2.0);
+}
@@ -0,0 +1,131 @@
+use std::f32::consts::PI;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    bsdf::Bsdf,
+    hit_list::{HitRec, HitState},
+    material::Material,
+    pbr::PbrMaterial,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::Vec3,
+    Color,
+};
+
+fn normal() -> Vec3 {
+    Vec3::new(0., 1., 0.)
+}
+
+/// Returns the hit at the top of the sphere, where the normal is `+y`.
+fn top(sphere: &Sphere) -> HitState<'_> {
+    let r = Ray::new((0., 5., 0.), (0., -1., 0.));
+    HitRec {
+        t: 4.0,
+        obj: sphere,
+    }
+    .prepare_computations(&r)
+}
+
+/// Estimates the fraction of light reflected towards `eyev` by importance
+/// sampling the BRDF.
+fn albedo(m: &PbrMaterial, eyev: Vec3, samples: u32) -> Color {
+    let sphere = Sphere::default();
+    let state = top(&sphere);
+    let mut sampler = Sampler::new(7);
+
+    let total = (0..samples).fold(Color::BLACK, |acc, _| {
+        match m.sample(&state, eyev, &mut sampler) {
+            Some(s) => acc + s.value * (s.lightv.dot(normal()) / s.pdf),
+            None => acc,
+        }
+    });
+
+    total * (1.0 / samples as f32)
+}
+
+#[test]
+fn energy_conserving() {
+    let eyes = [
+        Vec3::new(0., 1., 0.),
+        Vec3::new(0.6, 0.8, 0.),
+        Vec3::new(0., 0.1, 0.995).normalize(),
+    ];
+
+    for roughness in [0.05, 0.3, 0.7, 1.0] {
+        for metallic in [0.0, 0.5, 1.0] {
+            let m =
+                PbrMaterial::new([1., 1., 1.].into(), metallic, roughness).with_clearcoat(1.0, 0.2);
+            for eyev in eyes {
+                let a = albedo(&m, eyev, 4096).into_inner();
+                assert!(a[0] <= 1.02, "{roughness} {metallic} {eyev:?}: {a:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn sampling_matches_pdf() {
+    let sphere = Sphere::default();
+    let state = top(&sphere);
+    // compare importance sampling against uniform sampling of the hemisphere
+    let m = PbrMaterial::new([0.9, 0.5, 0.2].into(), 0.3, 0.4).with_clearcoat(0.5, 0.1);
+    let eyev = Vec3::new(0.3, 0.9, 0.1).normalize();
+
+    let mut s = Sampler::new(3);
+    let n = 200_000;
+    let mut uniform = Color::BLACK;
+    for _ in 0..n {
+        let (u, v) = s.next_2d();
+        let z = u;
+        let r = (1.0 - z * z).sqrt();
+        let phi = 2.0 * PI * v;
+        let l = Vec3::new(r * phi.cos(), z, r * phi.sin());
+        uniform = uniform + m.eval(&state, l, eyev) * (l.dot(normal()) * 2.0 * PI);
+    }
+    let uniform = uniform * (1.0 / n as f32);
+
+    assert_relative_eq!(albedo(&m, eyev, 20_000), uniform, max_relative = 0.03);
+}
+
+#[test]
+fn metals_reflect_their_base_color() {
+    let sphere = Sphere::default();
+    let state = top(&sphere);
+    let m = PbrMaterial::new([0.9, 0.6, 0.2].into(), 1.0, 0.1);
+    let eyev = Vec3::new(0., 1., 0.);
+
+    // a smooth metal seen head-on reflects close to its base colour
+    let a = albedo(&m, eyev, 1024);
+    assert_relative_eq!(a, [0.9, 0.6, 0.2].into(), max_relative = 0.05);
+
+    // and has no diffuse reflection away from the mirror direction
+    let l = Vec3::new(0.8, 0.6, 0.);
+    assert!(m.eval(&state, l, eyev).into_inner()[0] < 1e-3);
+}
+
+#[test]
+fn rough_dielectric_is_mostly_diffuse() {
+    let sphere = Sphere::default();
+    let state = top(&sphere);
+    let m = PbrMaterial::new([0.5, 0.5, 0.5].into(), 0.0, 1.0);
+    let l = Vec3::new(0.6, 0.8, 0.);
+    let eyev = Vec3::new(-0.6, 0.8, 0.);
+
+    let f = m.eval(&state, l, eyev).into_inner()[0];
+    assert!(f > 0.5 / PI * 0.9 && f < 0.5 / PI * 1.3);
+}
+
+#[test]
+fn material_uses_pbr() {
+    let sphere = Sphere::default();
+    let state = top(&sphere);
+    let pbr = PbrMaterial::new([0.2, 0.4, 0.6].into(), 0.0, 0.5);
+    let m = Material::default().with_pbr(pbr.clone());
+
+    let l = Vec3::new(0., 1., 0.);
+    let eyev = Vec3::new(0.6, 0.8, 0.);
+    assert_eq!(m.eval(&state, l, eyev), pbr.eval(&state, l, eyev));
+    assert_eq!(m.pdf(&state, l, eyev), pbr.pdf(&state, l, eyev));
+}
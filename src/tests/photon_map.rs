@@ -0,0 +1,117 @@
+use approx::assert_relative_eq;
+
+use crate::{
+    lights::{DirectionalLight, PointLight},
+    material::Material,
+    matrix::Mat4,
+    photon_map::{Photon, PhotonMap},
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::{Point3, Vec3},
+    world::World,
+    Color,
+};
+
+#[test]
+fn finds_photons_within_radius() {
+    let mut sampler = Sampler::new(5);
+    let photons: Vec<Photon> = (0..500)
+        .map(|_| Photon {
+            position: Point3::new(
+                sampler.next_f32() * 4.0,
+                sampler.next_f32() * 2.0,
+                sampler.next_f32(),
+            ),
+            lightv: Vec3::new(0., 1., 0.),
+            power: [1., 1., 1.].into(),
+        })
+        .collect();
+    let map = PhotonMap::new(photons.clone());
+    assert_eq!(map.len(), 500);
+
+    for point in [
+        Point3::new(1., 1., 0.5),
+        Point3::new(0., 0., 0.),
+        Point3::new(3.9, 0.2, 0.7),
+    ] {
+        let mut found: Vec<Photon> = map.within(point, 0.4).into_iter().copied().collect();
+        let mut expected: Vec<Photon> = photons
+            .iter()
+            .filter(|p| (p.position - point).mag() <= 0.4)
+            .copied()
+            .collect();
+
+        let key = |p: &Photon| (p.position.x(), p.position.y(), p.position.z());
+        found.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        expected.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap());
+        assert_eq!(found, expected);
+    }
+}
+
+/// A glass ball above a large floor, lit from straight above.
+fn glass_ball(light: impl Into<crate::lights::Light>) -> World {
+    let floor = Sphere::default().with_transform(
+        Mat4::new_scaling((1000., 1000., 1000.).into()).translate((0., -1000., 0.).into()),
+    );
+    let ball = Sphere::new(
+        Material::new([1., 1., 1.].into(), 0.0, 0.0, 0.0, 200.0).with_transparency(1.0, 1.5),
+    )
+    .with_transform(Mat4::new_translation((0., 3., 0.).into()));
+
+    World::new(vec![floor, ball], vec![light.into()])
+}
+
+#[test]
+fn glass_focuses_light() {
+    let light = DirectionalLight::new((0., -1., 0.), [1., 1., 1.]);
+    let plain = glass_ball(light.clone());
+    let world = glass_ball(light).with_caustics(20_000, 0.1);
+
+    // the photons carry the light falling onto the ball, minus what it
+    // reflects
+    let map = world.caustic_map().unwrap();
+    let total = map
+        .within(Point3::new(0., 0., 0.), 100.0)
+        .iter()
+        .fold(Color::BLACK, |acc, p| acc + p.power);
+    let total = total.into_inner()[0];
+    assert!(total > 0.8 * std::f32::consts::PI && total < std::f32::consts::PI);
+
+    // the floor below the ball is in the shadow of direct lighting, but
+    // lit by the light focused by the ball
+    let r = Ray::new((0., 1., -2.), (0., -1., 2.));
+    let without = plain.color_at(&r).into_inner()[0];
+    let with = world.color_at(&r).into_inner()[0];
+    assert_relative_eq!(without, 0.1);
+    assert!(with > 1.0, "{with}");
+}
+
+#[test]
+fn point_lights_cast_caustics() {
+    let light = PointLight::new((0., 10., 0.), [1., 1., 1.]);
+    let plain = glass_ball(light.clone());
+    let world = glass_ball(light).with_caustics(20_000, 0.1);
+
+    let r = Ray::new((0., 1., -2.), (0., -1., 2.));
+    assert!(world.color_at(&r).into_inner()[0] > 1.0);
+
+    // away from the ball, the floor has no caustic
+    let r = Ray::new((5., 1., -2.), (0., -1., 2.));
+    assert_relative_eq!(world.color_at(&r), plain.color_at(&r));
+}
+
+#[test]
+fn photons_are_traced_when_rendering() {
+    let dark = PointLight::new((0., 10., 0.), [0., 0., 0.]);
+    let mut world = glass_ball(dark).with_caustics(20_000, 0.1);
+    assert!(world.caustics.get().is_none());
+
+    // a light added after asking for caustics still casts them
+    world
+        .lights
+        .push(PointLight::new((0., 10., 0.), [1., 1., 1.]).into());
+    let r = Ray::new((0., 1., -2.), (0., -1., 2.));
+    assert!(world.color_at(&r).into_inner()[0] > 1.0);
+    assert!(world.caustics.get().is_some());
+}
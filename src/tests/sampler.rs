@@ -0,0 +1,68 @@
+use crate::sampler::{sample_disk, sample_polygon, Sampler};
+
+#[test]
+fn deterministic() {
+    let mut a = Sampler::new(42);
+    let mut b = Sampler::new(42);
+
+    for _ in 0..16 {
+        assert_eq!(a.next_u32(), b.next_u32());
+    }
+}
+
+#[test]
+fn different_pixels_differ() {
+    let a = Sampler::for_pixel(0, 0, 0).next_f32();
+    let b = Sampler::for_pixel(1, 0, 0).next_f32();
+    let c = Sampler::for_pixel(0, 0, 1).next_f32();
+
+    assert_ne!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn unit_interval() {
+    let mut s = Sampler::default();
+
+    let mut sum = 0.0;
+    for _ in 0..10_000 {
+        let v = s.next_f32();
+        assert!((0.0..1.0).contains(&v));
+        sum += v;
+    }
+
+    // the mean should be close to one half
+    assert!((sum / 10_000.0 - 0.5).abs() < 0.01);
+}
+
+#[test]
+fn disk_samples_inside_unit_disk() {
+    let mut s = Sampler::new(7);
+
+    assert_eq!(sample_disk(0.5, 0.5), (0.0, 0.0));
+    for _ in 0..1000 {
+        let (u, v) = s.next_2d();
+        let (x, y) = sample_disk(u, v);
+        assert!(x * x + y * y <= 1.0 + 1e-5);
+    }
+}
+
+#[test]
+fn polygon_samples_inside_polygon() {
+    let mut s = Sampler::new(7);
+    let sides = 6;
+
+    // the apothem of a hexagon inscribed in the unit circle
+    let apothem = (std::f32::consts::PI / sides as f32).cos();
+
+    for _ in 0..1000 {
+        let (u, v) = s.next_2d();
+        let (x, y) = sample_polygon(u, v, sides);
+
+        for i in 0..sides {
+            // distance along the normal of every edge
+            let angle = (i as f32 + 0.5) * 2.0 * std::f32::consts::PI / sides as f32;
+            assert!(x * angle.cos() + y * angle.sin() <= apothem + 1e-5);
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use approx::assert_relative_eq;
+
+use crate::Color;
+
+#[test]
+fn blackbody_is_normalized() {
+    for kelvin in [1900.0, 2700.0, 4000.0, 5600.0, 10000.0] {
+        let c = Color::from_temperature(kelvin);
+        assert_relative_eq!(c.luminance(), 1.0, epsilon = 0.05);
+    }
+}
+
+#[test]
+fn warm_and_cool_temperatures() {
+    let [r, g, b] = Color::from_temperature(2700.0).into_inner().0;
+    assert!(r > g && g > b);
+
+    let [r, g, b] = Color::from_temperature(12000.0).into_inner().0;
+    assert!(b > g && g > r);
+}
+
+#[test]
+fn daylight_is_nearly_white() {
+    let [r, g, b] = Color::from_temperature(6500.0).into_inner().0;
+
+    for c in [r, g, b] {
+        assert!((c - 1.0).abs() < 0.1, "{:?}", [r, g, b]);
+    }
+}
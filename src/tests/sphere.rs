@@ -1,4 +1,4 @@
-use std::f32::consts;
+use std::f32::consts::{self, FRAC_1_SQRT_2};
 
 use approx::assert_relative_eq;
 
@@ -7,6 +7,7 @@ use crate::{
     matrix::Mat4,
     ray::Ray,
     sphere::Sphere,
+    vec3::{Point3, Vec3},
 };
 
 #[test]
@@ -170,3 +171,66 @@ fn normal_at_transformed_sphere() {
     let n = s.normal_at((0., consts::FRAC_1_SQRT_2, -consts::FRAC_1_SQRT_2).into());
     assert_relative_eq!(n, (0.0, 0.97014, -0.24254).into());
 }
+
+#[test]
+fn tangent_is_perpendicular_to_normal() {
+    let s = Sphere::default().with_transform(Mat4::new_scaling((1., 0.5, 2.).into()).rotate_z(0.3));
+
+    for point in [
+        Point3::new(0.5, 0.2, 1.2),
+        Point3::new(-0.3, -0.4, -0.9),
+        Point3::new(0., 0.5, 0.),
+    ] {
+        let t = s.tangent_at(point);
+        assert_relative_eq!(t.mag(), 1.0, epsilon = 1e-5);
+        assert_relative_eq!(t.dot(s.normal_at(point)), 0.0, epsilon = 1e-5);
+    }
+
+    // on the unit sphere, the tangent follows the lines of latitude
+    let s = Sphere::default();
+    assert_relative_eq!(
+        s.tangent_at(Point3::new(0., 0., -1.)),
+        Vec3::new(-1., 0., 0.)
+    );
+}
+
+#[test]
+fn bounds_enclose_the_sphere() {
+    let s = Sphere::default().with_transform(
+        Mat4::new_scaling((1., 0.5, 2.).into())
+            .shear(0.5, 0., 0., 0., 0.3, 0.)
+            .translate((1., 2., 3.).into()),
+    );
+    let (centre, radius) = s.bounds();
+    assert_relative_eq!(centre, Point3::new(1., 2., 3.));
+
+    // the farthest point of the surface lies on the bounding sphere
+    let farthest = (0..10_000)
+        .map(|i| {
+            let p = s.point_on_surface((i % 100) as f32 / 100.0, (i / 100) as f32 / 100.0);
+            (p - centre).mag()
+        })
+        .fold(0.0, f32::max);
+    assert!(farthest <= radius + 1e-4);
+    assert_relative_eq!(farthest, radius, max_relative = 0.01);
+}
+
+#[test]
+fn uv_mapping_of_sphere() {
+    let s = Sphere::default().with_transform(Mat4::new_scaling((2., 2., 2.).into()));
+
+    let examples = [
+        ((0., 0., -2.), (0.0, 0.5)),
+        ((2., 0., 0.), (0.25, 0.5)),
+        ((0., 0., 2.), (0.5, 0.5)),
+        ((-2., 0., 0.), (0.75, 0.5)),
+        ((0., 2., 0.), (0.5, 1.0)),
+        ((0., -2., 0.), (0.5, 0.0)),
+        ((FRAC_1_SQRT_2 * 2., FRAC_1_SQRT_2 * 2., 0.), (0.25, 0.75)),
+    ];
+    for (point, (u, v)) in examples {
+        let (su, sv) = s.uv_at(point.into());
+        assert_relative_eq!(su, u, epsilon = 1e-5);
+        assert_relative_eq!(sv, v, epsilon = 1e-5);
+    }
+}
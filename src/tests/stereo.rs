@@ -0,0 +1,84 @@
+use std::f32::consts::FRAC_PI_2;
+
+use approx::assert_relative_eq;
+
+use crate::{
+    camera::Camera,
+    matrix::Mat4,
+    stereo::{Convergence, Eye, StereoCamera, StereoImage, StereoLayout},
+    vec3::Point3,
+};
+
+fn rig(convergence: Convergence) -> StereoCamera {
+    let camera = Camera::new(11, 11, FRAC_PI_2).with_transform(Mat4::view_transform(
+        (0., 0., -5.).into(),
+        (0., 0., 0.).into(),
+        (0., 1., 0.).into(),
+    ));
+
+    StereoCamera::new(camera, 0.5).with_convergence(convergence, 5.0)
+}
+
+#[test]
+fn parallel_eyes() {
+    let rig = rig(Convergence::Parallel);
+
+    let left = rig.eye(Eye::Left).ray_for_pixel(5, 5);
+    let right = rig.eye(Eye::Right).ray_for_pixel(5, 5);
+
+    // the camera looks along +z, so its left is -x
+    assert_relative_eq!(left.orig, (-0.25, 0., -5.).into());
+    assert_relative_eq!(right.orig, (0.25, 0., -5.).into());
+    assert_relative_eq!(left.dir, (0., 0., 1.).into());
+    assert_relative_eq!(right.dir, (0., 0., 1.).into());
+}
+
+#[test]
+fn converging_eyes_meet_at_convergence_point() {
+    for convergence in [Convergence::ToeIn, Convergence::OffAxis] {
+        let rig = rig(convergence);
+
+        for eye in [Eye::Left, Eye::Right] {
+            let r = rig.eye(eye).ray_for_pixel(5, 5);
+            let t = (0.0 - r.orig.z()) / r.dir.z();
+
+            assert_relative_eq!(r.pos(t), Point3::new(0., 0., 0.), epsilon = 1e-4);
+        }
+    }
+}
+
+#[test]
+fn off_axis_keeps_eyes_parallel() {
+    let rig = rig(Convergence::OffAxis);
+
+    // the corners of both views point in the same direction, only the
+    // window is shifted.
+    let left = rig.eye(Eye::Left);
+    let right = rig.eye(Eye::Right);
+    assert_relative_eq!(
+        left.ray_for_pixel(0, 5).dir.y(),
+        right.ray_for_pixel(0, 5).dir.y()
+    );
+    assert_relative_eq!(left.transform().clone(), {
+        let mut m = right.transform().clone();
+        m[(0, 3)] -= 0.5;
+        m
+    });
+}
+
+#[test]
+fn combine_layouts() {
+    let left = image::Rgb32FImage::from_pixel(2, 3, image::Rgb([1., 0., 0.]));
+    let right = image::Rgb32FImage::from_pixel(2, 3, image::Rgb([0., 0., 1.]));
+    let pair = StereoImage { left, right };
+
+    let sbs = pair.combine(StereoLayout::SideBySide);
+    assert_eq!(sbs.dimensions(), (4, 3));
+    assert_eq!(sbs.get_pixel(1, 2)[0], 1.0);
+    assert_eq!(sbs.get_pixel(2, 0)[2], 1.0);
+
+    let tb = pair.combine(StereoLayout::TopBottom);
+    assert_eq!(tb.dimensions(), (2, 6));
+    assert_eq!(tb.get_pixel(1, 2)[0], 1.0);
+    assert_eq!(tb.get_pixel(0, 3)[2], 1.0);
+}
@@ -121,3 +121,20 @@ fn reflection() {
     let n = Vec3::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.);
     assert_relative_eq!(v.reflect(n), (1., 0., 0.).into());
 }
+
+#[test]
+fn basis() {
+    for n in [
+        Vec3::new(0., 0., 1.),
+        Vec3::new(0., 0., -1.),
+        Vec3::new(1., 2., 3.).normalize(),
+    ] {
+        let (t, b) = n.basis();
+
+        assert_relative_eq!(t.mag(), 1.0);
+        assert_relative_eq!(b.mag(), 1.0);
+        assert_relative_eq!(t.dot(b), 0.0);
+        assert_relative_eq!(t.dot(n), 0.0);
+        assert_relative_eq!(b.dot(n), 0.0);
+    }
+}
@@ -0,0 +1,153 @@
+use approx::assert_relative_eq;
+
+use crate::{
+    integrator::{Integrator, PathTracer},
+    lights::{LightSample, PointLight},
+    material::Material,
+    matrix::Mat4,
+    medium::Medium,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::Point3,
+    volume::{DensityGrid, Volume, VolumeError},
+    world::World,
+    Color,
+};
+
+const GRID: &str = "
+# dimensions and bounds
+2 1 1
+0 0 0  2 1 1
+0.0 1.0
+";
+
+#[test]
+fn parse_text() {
+    let grid = DensityGrid::parse_text(GRID).unwrap();
+
+    assert_eq!(grid.max_density(), 1.0);
+    // the samples sit at the centres of the cells
+    assert_relative_eq!(grid.density(Point3::new(0.5, 0.5, 0.5)), 0.0);
+    assert_relative_eq!(grid.density(Point3::new(1.0, 0.5, 0.5)), 0.5);
+    assert_relative_eq!(grid.density(Point3::new(1.25, 0.2, 0.9)), 0.75);
+    assert_relative_eq!(grid.density(Point3::new(1.9, 0.5, 0.5)), 1.0);
+    assert_relative_eq!(grid.density(Point3::new(2.1, 0.5, 0.5)), 0.0);
+}
+
+#[test]
+fn parse_raw() {
+    let mut bytes = Vec::new();
+    for d in [2u32, 1, 1] {
+        bytes.extend(d.to_le_bytes());
+    }
+    for x in [0f32, 0., 0., 2., 1., 1., 0., 1.] {
+        bytes.extend(x.to_le_bytes());
+    }
+
+    assert_eq!(
+        DensityGrid::parse_raw(&bytes).unwrap(),
+        DensityGrid::parse_text(GRID).unwrap()
+    );
+
+    bytes.truncate(bytes.len() - 4);
+    assert!(matches!(
+        DensityGrid::parse_raw(&bytes),
+        Err(VolumeError::UnexpectedEnd)
+    ));
+}
+
+#[test]
+fn invalid_grids() {
+    assert!(matches!(
+        DensityGrid::parse_text("2 1 1 0 0 0 2 1 1 0.5"),
+        Err(VolumeError::UnexpectedEnd)
+    ));
+    assert!(matches!(
+        DensityGrid::parse_text("2 1 1 0 0 0 2 1 1 0.5 1 2"),
+        Err(VolumeError::TrailingData)
+    ));
+    assert!(matches!(
+        DensityGrid::parse_text("0 1 1 0 0 0 2 1 1"),
+        Err(VolumeError::InvalidDimensions)
+    ));
+    assert!(matches!(
+        DensityGrid::parse_text("1 1 1 0 0 0 2 1 1 fog"),
+        Err(VolumeError::InvalidNumber(_))
+    ));
+
+    // the number of samples overflows
+    assert!(matches!(
+        DensityGrid::parse_text("1e30 1e30 1 0 0 0 1 1 1 0.5"),
+        Err(VolumeError::InvalidDimensions)
+    ));
+    let header: Vec<u8> = [u32::MAX, u32::MAX, u32::MAX]
+        .iter()
+        .flat_map(|d| d.to_le_bytes())
+        .chain(
+            [0.0f32, 0., 0., 1., 1., 1.]
+                .iter()
+                .flat_map(|v| v.to_le_bytes()),
+        )
+        .collect();
+    assert!(matches!(
+        DensityGrid::parse_raw(&header),
+        Err(VolumeError::InvalidDimensions)
+    ));
+}
+
+/// A cube of absorbing smoke with a density of one from `-1` to `1`.
+fn smoke() -> Volume {
+    let grid = DensityGrid::new(
+        [4, 4, 4],
+        Point3::new(0., 0., 0.),
+        Point3::new(1., 1., 1.),
+        vec![1.0; 64],
+    )
+    .unwrap();
+    let medium = Medium::new([0.5, 0.5, 0.5].into(), Color::BLACK, 0.0);
+
+    Volume::new(grid, medium)
+        .with_transform(Mat4::new_scaling((2., 2., 2.).into()).translate((-1., -1., -1.).into()))
+}
+
+#[test]
+fn ratio_tracking() {
+    let w = World::new(Vec::new(), Vec::<PointLight>::new()).with_volume(smoke());
+    let point = Point3::new(0., 0., -5.);
+    let mut sampler = Sampler::new(3);
+
+    // the light passes through two units of smoke
+    let through = LightSample::towards(point, Point3::new(0., 0., 5.), [1., 1., 1.].into());
+    let n = 4096;
+    let total = (0..n).fold(Color::BLACK, |acc, _| {
+        acc + w.transmittance(point, &through, &mut sampler)
+    });
+    let tr = (total * (1.0 / n as f32)).into_inner()[0];
+    assert_relative_eq!(tr, (-1.0f32).exp(), epsilon = 0.02);
+
+    // and misses the smoke which was moved away from the origin
+    let beside = LightSample::towards(point, Point3::new(0., 1.5, 5.), [1., 1., 1.].into());
+    assert_relative_eq!(w.transmittance(point, &beside, &mut sampler), Color::WHITE);
+}
+
+#[test]
+fn delta_tracking() {
+    let glow = Sphere::new(
+        Material::default()
+            .with_ambient(0.0)
+            .with_emission([1., 1., 1.].into(), 1.0),
+    )
+    .with_transform(Mat4::new_translation((0., 0., 10.).into()));
+    let w = World::new(vec![glow], Vec::<PointLight>::new())
+        .with_volume(smoke())
+        .with_integrator(Integrator::PathTracer(PathTracer::new(4)));
+
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let n = 1024;
+    let total = (0..n).fold(Color::BLACK, |acc, i| {
+        acc + w.color_at_sampled(&r, &mut Sampler::new(i))
+    });
+    let c = (total * (1.0 / n as f32)).into_inner()[0];
+    assert_relative_eq!(c, (-1.0f32).exp(), epsilon = 0.05);
+}
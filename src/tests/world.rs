@@ -1,8 +1,18 @@
+use std::f32::consts::{FRAC_PI_4, FRAC_PI_6};
+
 use approx::assert_relative_eq;
 
 use crate::{
-    hit_list::HitRec, lights::PointLight, material::Material, matrix::Mat4, ray::Ray,
-    sphere::Sphere, world::World, Color,
+    hit_list::HitRec,
+    lights::{AreaLight, DirectionalLight, Light, LightSample, PointLight, SpotLight},
+    material::Material,
+    matrix::Mat4,
+    ray::Ray,
+    sampler::Sampler,
+    sphere::Sphere,
+    vec3::Point3,
+    world::World,
+    Color,
 };
 
 pub fn default_world() -> World {
@@ -51,7 +61,7 @@ fn shading() {
 fn shading_from_inside() {
     let mut world = default_world();
     if let Some(light) = world.lights.first_mut() {
-        *light = PointLight::new((0., 0.25, 0.), [1., 1., 1.]);
+        *light = PointLight::new((0., 0.25, 0.), [1., 1., 1.]).into();
     }
 
     let ray = Ray::new((0., 0., 0.), (0., 0., 1.));
@@ -95,3 +105,241 @@ fn color_when_intersection_behind_ray() {
     let inner = &w.objects[1];
     assert_relative_eq!(w.color_at(&r), inner.material().color);
 }
+
+fn shadowed(w: &World, point: Point3) -> bool {
+    let light = PointLight::new((-10., 10., -10.), [1., 1., 1.]);
+    w.is_shadowed(point, &light.sample(point))
+}
+
+#[test]
+fn no_shadow_when_nothing_collinear() {
+    let w = default_world();
+    assert!(!shadowed(&w, Point3::new(0., 10., 0.)));
+}
+
+#[test]
+fn shadow_when_object_between_point_and_light() {
+    let w = default_world();
+    assert!(shadowed(&w, Point3::new(10., -10., 10.)));
+}
+
+#[test]
+fn no_shadow_when_object_behind_light_or_point() {
+    let w = default_world();
+    assert!(!shadowed(&w, Point3::new(-20., 20., -20.)));
+    assert!(!shadowed(&w, Point3::new(-2., 2., -2.)));
+}
+
+#[test]
+fn shade_hit_in_shadow() {
+    let s1 = Sphere::default();
+    let s2 = Sphere::default().with_transform(Mat4::new_translation((0., 0., 10.).into()));
+    let w = World::new(
+        vec![s1, s2],
+        vec![PointLight::new((0., 0., -10.), [1., 1., 1.])],
+    );
+
+    let r = Ray::new((0., 0., 5.), (0., 0., 1.));
+    let hit = HitRec {
+        t: 4.0,
+        obj: &w.objects[1],
+    };
+
+    // only the ambient term remains
+    let comps = hit.prepare_computations(&r);
+    assert_relative_eq!(w.shade_hit(comps), [0.1, 0.1, 0.1].into());
+}
+
+#[test]
+fn hit_offsets_over_point() {
+    let r = Ray::new((0., 0., -5.), (0., 0., 1.));
+    let s = Sphere::default().with_transform(Mat4::new_translation((0., 0., 1.).into()));
+    let hit = HitRec { t: 5.0, obj: &s };
+
+    let comps = hit.prepare_computations(&r);
+    assert!(comps.over_point.z() < -crate::hit_list::EPSILON / 2.0);
+    assert!(comps.point.z() > comps.over_point.z());
+}
+
+#[test]
+fn area_light_gives_penumbra() {
+    // a unit sphere between a square light and the floor at y = 0
+    let blocker = Sphere::default().with_transform(Mat4::new_translation((0., 2., 0.).into()));
+    let light = AreaLight::new_rect((-1., 4., -1.), (2., 0., 0.), (0., 0., 2.), [1., 1., 1.])
+        .with_samples(8, 8);
+    let w = World::new(vec![blocker], vec![light.clone()]);
+
+    let fraction_lit = |point: Point3| {
+        let samples = light.samples(point, &mut Sampler::default());
+        let lit = samples.iter().filter(|s| !w.is_shadowed(point, s)).count();
+        lit as f32 / samples.len() as f32
+    };
+
+    // umbra, penumbra and fully lit
+    assert_eq!(fraction_lit(Point3::new(0., 0., 0.)), 0.0);
+    let partial = fraction_lit(Point3::new(1.5, 0., 0.));
+    assert!(partial > 0.0 && partial < 1.0);
+    assert_eq!(fraction_lit(Point3::new(5., 0., 0.)), 1.0);
+}
+
+#[test]
+fn light_samples_share_intensity() {
+    let light =
+        AreaLight::new_disk((0., 5., 0.), (0., -1., 0.), 1.0, [1., 0.5, 0.25]).with_samples(3, 2);
+    let samples = light.samples(Point3::default(), &mut Sampler::default());
+
+    // a disk of radius `R` at height `h` delivers
+    // `2 I h / R^2 (1 / h - 1 / sqrt(h^2 + R^2))` to the point below
+    assert_eq!(samples.len(), 6);
+    let total = samples
+        .iter()
+        .fold(Color::BLACK, |acc, s: &LightSample| acc + s.intensity);
+    let arriving = 10.0 * (0.2 - 1.0 / 26.0f32.sqrt());
+    assert_relative_eq!(
+        total,
+        Color::from([1., 0.5, 0.25]) * arriving,
+        max_relative = 0.02
+    );
+
+    for s in samples {
+        assert!(s.distance >= 5.0 && s.distance <= 26.0f32.sqrt() + 1e-4);
+    }
+}
+
+#[test]
+fn spot_light_only_lights_its_cone() {
+    let floor = Sphere::default().with_transform(Mat4::new_scaling((10., 0.01, 10.).into()));
+    let light = SpotLight::new(
+        (0., 5., 0.),
+        (0., -1., 0.),
+        FRAC_PI_6,
+        FRAC_PI_4,
+        [1., 1., 1.],
+    );
+    let w = World::new(vec![floor], vec![light]);
+
+    let inside = w.color_at(&Ray::new((0., 1., 0.), (0., -1., 0.)));
+    let outside = w.color_at(&Ray::new((8., 1., 0.), (0., -1., 0.)));
+
+    assert!(inside.into_inner()[0] > 0.5);
+    // only the ambient term remains outside the cone
+    assert_relative_eq!(outside, [0.1, 0.1, 0.1].into());
+}
+
+#[test]
+fn directional_light_shadows_reach_infinity() {
+    let w = default_world();
+    let sun = DirectionalLight::new((0., -1., 0.), [1., 1., 1.]);
+
+    // however far below the spheres, the point stays in shadow
+    assert!(w.is_shadowed(Point3::new(0., -1000., 0.), &sun.sample()));
+    assert!(!w.is_shadowed(Point3::new(2., -1000., 0.), &sun.sample()));
+}
+
+#[test]
+fn mixed_lights() {
+    let lights: Vec<Light> = vec![
+        PointLight::new((-10., 10., -10.), [0.5, 0.5, 0.5]).into(),
+        DirectionalLight::new((1., -1., 1.), [0.5, 0.5, 0.5]).into(),
+        SpotLight::new((0., 0., -10.), (0., 0., 1.), 0.1, 0.2, [1., 1., 1.]).into(),
+        AreaLight::new_rect((-1., 5., -5.), (2., 0., 0.), (0., 0., 2.), [1., 1., 1.]).into(),
+    ];
+    let w = World::new(default_world().objects, lights);
+
+    assert_eq!(w.lights.len(), 4);
+    let c = w.color_at(&Ray::new((0., 0., -5.), (0., 0., 1.)));
+    assert!(c.into_inner()[1] > 0.5);
+}
+
+#[test]
+fn emissive_objects_are_lights() {
+    let floor = Sphere::default().with_transform(Mat4::new_scaling((10., 0.01, 10.).into()));
+    let panel = Sphere::new(Material::default().with_emission([1., 0.5, 0.].into(), 2.0))
+        .with_transform(Mat4::new_translation((0., 3., 0.).into()));
+    let w = World::new(vec![floor, panel], Vec::<Light>::new());
+
+    let seen = w.color_at(&Ray::new((0., 3., -5.), (0., 0., 1.)));
+    assert_relative_eq!(seen, [2., 1., 0.].into());
+
+    // without any lights, the floor is only lit by the glowing sphere
+    let floor = w.color_at(&Ray::new((0., 1., 0.), (0., -1., 0.)));
+    assert!(floor.into_inner()[0] > 0.0);
+    assert_relative_eq!(floor.into_inner()[2], 0.0);
+}
+
+#[test]
+fn emitters_do_not_shadow_themselves() {
+    let panel = Sphere::new(Material::default().with_emission([1., 1., 1.].into(), 1.0))
+        .with_transform(Mat4::new_translation((0., 3., 0.).into()));
+    let blocker = Sphere::default().with_transform(Mat4::new_translation((5., 3., 0.).into()));
+    let w = World::new(vec![panel, blocker], Vec::<Light>::new());
+    let panel = &w.objects[0];
+    let mut sampler = Sampler::default();
+
+    // the samples end right on the surface of the emitter
+    let point = Point3::new(0., 0., 0.);
+    for (sample, _) in w.emitter_samples(panel, point, &mut sampler) {
+        let tr = w.transmittance_from(point, &sample, Some(panel), &mut sampler);
+        assert_relative_eq!(tr, Color::WHITE);
+    }
+
+    // other objects still cast shadows
+    let point = Point3::new(8., 3., 0.);
+    for (sample, _) in w.emitter_samples(panel, point, &mut sampler) {
+        let tr = w.transmittance_from(point, &sample, Some(panel), &mut sampler);
+        assert_relative_eq!(tr, Color::BLACK);
+    }
+}
+
+fn glass(transform: Mat4, refractive_index: f32) -> Sphere {
+    Sphere::new(Material::default().with_transparency(1.0, refractive_index))
+        .with_transform(transform)
+}
+
+#[test]
+fn refractive_indices_of_nested_objects() {
+    let a = glass(Mat4::new_scaling((2., 2., 2.).into()), 1.5);
+    let b = glass(Mat4::new_translation((0., 0., -0.25).into()), 2.0);
+    let c = glass(Mat4::new_translation((0., 0., 0.25).into()), 2.5);
+    let world = World::new(vec![a, b, c], Vec::<PointLight>::new());
+
+    let expected = [
+        (1.0, 1.5),
+        (1.5, 2.0),
+        (2.0, 2.5),
+        (2.5, 2.5),
+        (2.5, 1.5),
+        (1.5, 1.0),
+    ];
+    let ts = [2.0, 2.75, 3.25, 4.75, 5.25, 6.0];
+    for (t, (n1, n2)) in ts.into_iter().zip(expected) {
+        // start every ray just before the next hit
+        let r = Ray::new((0., 0., -4.0 + t - 0.01), (0., 0., 1.));
+        let state = world.intersect(&r).prepare_hit(&r).unwrap();
+
+        assert_eq!((state.n1, state.n2), (n1, n2), "t = {t}");
+    }
+}
+
+#[test]
+fn thick_glass_absorbs_more_light() {
+    let glow = Sphere::new(
+        Material::default()
+            .with_ambient(0.0)
+            .with_emission([1., 1., 1.].into(), 1.0),
+    )
+    .with_transform(Mat4::new_translation((0., 0., 10.).into()).scale((5., 5., 5.).into()));
+    let ball = Sphere::new(
+        Material::new([1., 1., 1.].into(), 0.0, 0.0, 0.0, 200.0)
+            .with_transparency(1.0, 1.0)
+            .with_absorption([0.5, 0.8, 1.0].into(), 1.0),
+    );
+    let world = World::new(vec![glow, ball], Vec::<PointLight>::new());
+
+    // through the centre, the light travels two units inside the ball
+    let centre = world.color_at(&Ray::new((0., 0., -5.), (0., 0., 1.)));
+    assert_relative_eq!(centre, [0.25, 0.64, 1.0].into(), epsilon = 2e-3);
+
+    let edge = world.color_at(&Ray::new((0., 0.9, -5.), (0., 0., 1.)));
+    assert!(edge.into_inner()[0] > centre.into_inner()[0]);
+}
@@ -0,0 +1,90 @@
+//! Splits the image into tiles and distributes them across worker threads.
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// A rectangular block of pixels rendered as a unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Tile {
+    /// Column of the top-left pixel of the tile.
+    pub(crate) x0: u32,
+    /// Row of the top-left pixel of the tile.
+    pub(crate) y0: u32,
+    /// Width of the tile in pixels.
+    pub(crate) width: u32,
+    /// Height of the tile in pixels.
+    pub(crate) height: u32,
+}
+
+impl Tile {
+    /// Splits a `width x height` image into tiles of at most `size x size`
+    /// pixels, in scanline order.
+    pub(crate) fn split(width: u32, height: u32, size: u32) -> Vec<Tile> {
+        let size = size.max(1);
+        let mut tiles = Vec::new();
+
+        for y0 in (0..height).step_by(size as usize) {
+            for x0 in (0..width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x0,
+                    y0,
+                    width: size.min(width - x0),
+                    height: size.min(height - y0),
+                });
+            }
+        }
+
+        tiles
+    }
+
+    /// Returns an iterator over the pixels of the tile in scanline order.
+    pub(crate) fn pixels(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        (self.y0..self.y0 + self.height)
+            .flat_map(move |y| (self.x0..self.x0 + self.width).map(move |x| (x, y)))
+    }
+}
+
+/// Calls `f` for every item of `items` on up to `threads` worker threads.
+///
+/// Items are handed out dynamically so that busy threads do not hold up
+/// idle ones. The results are returned in the order of `items`, regardless
+/// of the order in which they were computed.
+pub(crate) fn for_each_parallel<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.clamp(1, items.len().max(1));
+    if threads == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(i) {
+                            Some(item) => done.push((i, f(item))),
+                            None => break done,
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("render thread panicked"))
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
@@ -138,6 +138,21 @@ impl Vec3 {
     pub fn reflect(&self, n: Vec3) -> Self {
         *self - n * self.dot(n) * 2.0
     }
+
+    /// Returns two unit vectors which are perpendicular to each other and
+    /// to `self`. `self` must be normalized.
+    pub fn basis(&self) -> (Self, Self) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited"
+        let (x, y, z) = (self.x(), self.y(), self.z());
+        let sign = 1.0f32.copysign(z);
+        let a = -1.0 / (sign + z);
+        let b = x * y * a;
+
+        (
+            Vec3::new(1.0 + sign * x * x * a, sign * b, -sign * x),
+            Vec3::new(b, sign + y * y * a, -y),
+        )
+    }
 }
 
 impl From<(f32, f32, f32)> for Vec3 {
@@ -291,7 +306,7 @@ impl AbsDiffEq for Point3 {
     fn abs_diff_eq(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
         self.0
             .into_iter()
-            .zip(other.0.into_iter())
+            .zip(other.0)
             .take(3)
             .all(|(l, r)| f32::abs_diff_eq(&l, &r, epsilon))
     }
@@ -309,7 +324,7 @@ impl RelativeEq for Point3 {
     ) -> bool {
         self.0
             .into_iter()
-            .zip(other.0.into_iter())
+            .zip(other.0)
             .take(3)
             .all(|(l, r)| f32::relative_eq(&l, &r, epsilon, max_relative))
     }
@@ -324,7 +339,7 @@ impl AbsDiffEq for Vec3 {
     fn abs_diff_eq(&self, other: &Self, epsilon: Self::Epsilon) -> bool {
         self.0
             .into_iter()
-            .zip(other.0.into_iter())
+            .zip(other.0)
             .take(3)
             .all(|(l, r)| f32::abs_diff_eq(&l, &r, epsilon))
     }
@@ -342,7 +357,7 @@ impl RelativeEq for Vec3 {
     ) -> bool {
         self.0
             .into_iter()
-            .zip(other.0.into_iter())
+            .zip(other.0)
             .take(3)
             .all(|(l, r)| f32::relative_eq(&l, &r, epsilon, max_relative))
     }
@@ -0,0 +1,321 @@
+//! Heterogeneous participating media such as clouds and smoke.
+//!
+//! The density of the medium is given by a regular grid of samples, as
+//! written by fluid simulations, and interpolated trilinearly in between.
+//! A [`Volume`] places a [`DensityGrid`] in the scene and scales the
+//! coefficients of a [`Medium`] by the density.
+//!
+//! Grids are read from two simple formats. Both start with a header giving
+//! the number of samples along `x`, `y` and `z`, followed by the minimum and
+//! maximum corners of the box covered by the grid. Then follow the
+//! densities, with `x` varying fastest and `z` slowest.
+//!
+//! - The text format lists all the numbers separated by whitespace. Lines
+//!   starting with `#` are comments.
+//! - The raw format stores the dimensions as little-endian `u32` and the
+//!   bounds and densities as little-endian `f32`.
+
+use std::{error, fmt, fs, io, path::Path, sync::Arc};
+
+use crate::{matrix::Mat4, medium::Medium, ray::Ray, sampler::Sampler, vec3::Point3, Color};
+
+/// Errors which can occur while reading a density grid.
+#[derive(Debug)]
+pub enum VolumeError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// A value could not be parsed as a number.
+    InvalidNumber(String),
+    /// The file ended before all the values were read.
+    UnexpectedEnd,
+    /// The file contains more values than the grid has samples.
+    TrailingData,
+    /// A dimension is zero, the grid has too many samples or the bounds do
+    /// not enclose any space.
+    InvalidDimensions,
+}
+
+impl fmt::Display for VolumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read density grid: {}", e),
+            Self::InvalidNumber(s) => write!(f, "invalid number `{}`", s),
+            Self::UnexpectedEnd => write!(f, "unexpected end of file"),
+            Self::TrailingData => write!(f, "more densities than grid samples"),
+            Self::InvalidDimensions => write!(f, "invalid grid dimensions"),
+        }
+    }
+}
+
+impl error::Error for VolumeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VolumeError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A regular grid of density samples covering an axis-aligned box.
+///
+/// The samples sit at the centres of the cells the box is divided into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    /// Number of samples along `x`, `y` and `z`.
+    dims: [usize; 3],
+    /// Minimum corner of the box.
+    min: Point3,
+    /// Maximum corner of the box.
+    max: Point3,
+    /// Densities, `x` varying fastest.
+    densities: Vec<f32>,
+    /// The largest density.
+    max_density: f32,
+}
+
+impl DensityGrid {
+    /// Constructs a new `DensityGrid` covering the box from `min` to `max`.
+    /// Negative densities, which simulations sometimes produce, are clamped
+    /// to zero.
+    pub fn new(
+        dims: [usize; 3],
+        min: Point3,
+        max: Point3,
+        densities: Vec<f32>,
+    ) -> Result<Self, VolumeError> {
+        if dims.contains(&0) || (0..3).any(|i| min[i] >= max[i]) {
+            return Err(VolumeError::InvalidDimensions);
+        }
+
+        let count = dims[0]
+            .checked_mul(dims[1])
+            .and_then(|n| n.checked_mul(dims[2]))
+            .ok_or(VolumeError::InvalidDimensions)?;
+        if densities.len() < count {
+            return Err(VolumeError::UnexpectedEnd);
+        } else if densities.len() > count {
+            return Err(VolumeError::TrailingData);
+        }
+
+        let densities: Vec<f32> = densities.into_iter().map(|d| d.max(0.0)).collect();
+        let max_density = densities.iter().copied().fold(0.0, f32::max);
+
+        Ok(Self {
+            dims,
+            min,
+            max,
+            densities,
+            max_density,
+        })
+    }
+
+    /// Reads the grid from a file in the text format.
+    pub fn load_text(path: impl AsRef<Path>) -> Result<Self, VolumeError> {
+        let text = fs::read_to_string(path)?;
+        Self::parse_text(&text)
+    }
+
+    /// Reads the grid from a file in the raw format.
+    pub fn load_raw(path: impl AsRef<Path>) -> Result<Self, VolumeError> {
+        let bytes = fs::read(path)?;
+        Self::parse_raw(&bytes)
+    }
+
+    /// Parses a grid in the text format.
+    pub fn parse_text(text: &str) -> Result<Self, VolumeError> {
+        let mut values = text
+            .lines()
+            .filter(|l| !l.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            .map(|s| {
+                s.parse::<f32>()
+                    .map_err(|_| VolumeError::InvalidNumber(s.into()))
+            });
+        let mut next = || values.next().unwrap_or(Err(VolumeError::UnexpectedEnd));
+
+        let mut dims = [0; 3];
+        for d in &mut dims {
+            let value = next()?;
+            if value.fract() != 0.0 || value < 0.0 {
+                return Err(VolumeError::InvalidDimensions);
+            }
+            *d = value as usize;
+        }
+        let min = Point3::new(next()?, next()?, next()?);
+        let max = Point3::new(next()?, next()?, next()?);
+
+        let densities = values.collect::<Result<Vec<_>, _>>()?;
+        Self::new(dims, min, max, densities)
+    }
+
+    /// Parses a grid in the raw format.
+    pub fn parse_raw(bytes: &[u8]) -> Result<Self, VolumeError> {
+        let mut words = bytes
+            .chunks(4)
+            .map(|c| <[u8; 4]>::try_from(c).map_err(|_| VolumeError::TrailingData));
+        let mut next = || words.next().unwrap_or(Err(VolumeError::UnexpectedEnd));
+
+        let mut dims = [0; 3];
+        for d in &mut dims {
+            *d = u32::from_le_bytes(next()?) as usize;
+        }
+        let mut float = || next().map(f32::from_le_bytes);
+        let min = Point3::new(float()?, float()?, float()?);
+        let max = Point3::new(float()?, float()?, float()?);
+
+        let densities = words
+            .map(|w| w.map(f32::from_le_bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(dims, min, max, densities)
+    }
+
+    /// Returns the largest density of the grid.
+    pub fn max_density(&self) -> f32 {
+        self.max_density
+    }
+
+    /// Returns the density at `point`, interpolated trilinearly between the
+    /// samples around it. Between the outermost samples and the sides of
+    /// the box, the density is constant. Outside the box, it is zero.
+    pub fn density(&self, point: Point3) -> f32 {
+        let mut cell = [0; 3];
+        let mut frac = [0.0; 3];
+        for i in 0..3 {
+            let x = (point[i] - self.min[i]) / (self.max[i] - self.min[i]);
+            if !(0.0..=1.0).contains(&x) {
+                return 0.0;
+            }
+
+            // position relative to the sample centres
+            let n = self.dims[i];
+            let x = (x * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            cell[i] = (x as usize).min(n.saturating_sub(2));
+            frac[i] = x - cell[i] as f32;
+        }
+
+        let at = |dx: usize, dy: usize, dz: usize| {
+            let x = (cell[0] + dx).min(self.dims[0] - 1);
+            let y = (cell[1] + dy).min(self.dims[1] - 1);
+            let z = (cell[2] + dz).min(self.dims[2] - 1);
+            self.densities[x + self.dims[0] * (y + self.dims[1] * z)]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        let [fx, fy, fz] = frac;
+        let y0 = lerp(
+            lerp(at(0, 0, 0), at(1, 0, 0), fx),
+            lerp(at(0, 1, 0), at(1, 1, 0), fx),
+            fy,
+        );
+        let y1 = lerp(
+            lerp(at(0, 0, 1), at(1, 0, 1), fx),
+            lerp(at(0, 1, 1), at(1, 1, 1), fx),
+            fy,
+        );
+
+        lerp(y0, y1, fz)
+    }
+
+    /// Returns the range of the ray inside the box of the grid, if any.
+    fn intersect(&self, r: &Ray) -> Option<(f32, f32)> {
+        let (mut t0, mut t1) = (f32::NEG_INFINITY, f32::INFINITY);
+        for i in 0..3 {
+            let inv = 1.0 / r.dir[i];
+            let a = (self.min[i] - r.orig[i]) * inv;
+            let b = (self.max[i] - r.orig[i]) * inv;
+            if a.is_nan() || b.is_nan() {
+                // the ray runs within the plane of a side
+                continue;
+            }
+
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+        }
+
+        (t0 < t1).then_some((t0, t1))
+    }
+}
+
+/// A density grid placed in the scene, filled with a medium whose
+/// coefficients are scaled by the density.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    grid: Arc<DensityGrid>,
+    medium: Medium,
+    transform: Mat4,
+    transform_inv: Mat4,
+}
+
+impl Volume {
+    /// Constructs a new `Volume`. The coefficients of `medium` give the
+    /// medium at a density of one.
+    pub fn new(grid: impl Into<Arc<DensityGrid>>, medium: Medium) -> Self {
+        Self {
+            grid: grid.into(),
+            medium,
+            transform: Mat4::identity(),
+            transform_inv: Mat4::identity(),
+        }
+    }
+
+    /// Set the transform placing the grid in the scene.
+    pub fn with_transform(mut self, transform: Mat4) -> Self {
+        self.transform_inv = transform.inverse().unwrap_or_else(Mat4::identity);
+        self.transform = transform;
+        self
+    }
+
+    /// Returns the medium at `point`, or `None` outside of the grid.
+    pub fn medium_at(&self, point: Point3) -> Option<Medium> {
+        let density = self.grid.density(&self.transform_inv * point);
+        (density > 0.0).then(|| self.medium.scaled(density))
+    }
+
+    /// Returns an upper bound of the extinction coefficient in the volume.
+    pub(crate) fn majorant(&self) -> f32 {
+        self.grid.max_density * self.medium.sigma_t().max_component()
+    }
+
+    /// Returns the range of the ray up to `t_max` which lies inside the
+    /// volume, if any.
+    pub(crate) fn interval(&self, r: &Ray, t_max: f32) -> Option<(f32, f32)> {
+        let (t0, t1) = self.grid.intersect(&r.transform(&self.transform_inv))?;
+        let (t0, t1) = (t0.max(0.0), t1.min(t_max));
+
+        (t0 < t1).then_some((t0, t1))
+    }
+
+    /// Estimates the fraction of light passing through the volume along the
+    /// ray up to `t_max` by ratio tracking.
+    pub(crate) fn transmittance(&self, r: &Ray, t_max: f32, sampler: &mut Sampler) -> Color {
+        let majorant = self.majorant();
+        let (t0, t1) = match self.interval(r, t_max) {
+            Some(interval) if majorant > 0.0 => interval,
+            _ => return Color::WHITE,
+        };
+
+        let speed = r.dir.mag();
+        let mut tr = Color::WHITE;
+        let mut t = t0;
+        loop {
+            t += -(1.0 - sampler.next_f32()).ln() / (majorant * speed);
+            if t >= t1 {
+                return tr;
+            }
+
+            if let Some(medium) = self.medium_at(r.pos(t)) {
+                tr = tr.blend(Color::WHITE - medium.sigma_t() * (1.0 / majorant));
+            }
+
+            if tr.max_component() <= 0.0 {
+                return Color::BLACK;
+            }
+        }
+    }
+}
@@ -2,25 +2,120 @@
 //! and the routines for intersecting that world with a ray and computing
 //! the colours.
 
+use std::{f32::consts::PI, sync::OnceLock};
+
 use crate::{
     hit_list::{HitList, HitState},
-    lights::PointLight,
+    integrator::{AmbientOcclusion, Integrator},
+    lights::{Light, LightSample},
+    medium::Medium,
+    photon_map::{self, PhotonMap},
     ray::Ray,
+    sampler::Sampler,
     sphere::Sphere,
+    vec3::{Point3, Vec3},
+    volume::Volume,
     Color,
 };
 
+/// Number of cells along each side of the grid of jittered samples taken
+/// on the surface of an emissive object.
+const EMITTER_GRID: u32 = 4;
+
+/// Number of steps taken through every piece of a medium when ray
+/// marching.
+const MARCH_STEPS: u32 = 16;
+
+/// Number of times a ray of the Whitted integrator is reflected or
+/// refracted by transparent objects before it is cut off.
+const MAX_BOUNCES: u32 = 5;
+
 /// A collection of objects and lights in a scene.
+///
+/// Besides the lights, every object with an emissive material lights
+/// up the scene.
 #[derive(Debug)]
 pub struct World {
     pub(crate) objects: Vec<Sphere>,
-    pub(crate) lights: Vec<PointLight>,
+    pub(crate) lights: Vec<Light>,
+    pub(crate) integrator: Integrator,
+    pub(crate) fog: Option<Medium>,
+    pub(crate) fog_height: f32,
+    pub(crate) volumes: Vec<Volume>,
+    pub(crate) caustic_photons: u32,
+    pub(crate) caustic_radius: f32,
+    pub(crate) caustics: OnceLock<PhotonMap>,
+    pub(crate) ambient_occlusion: Option<AmbientOcclusion>,
 }
 
 impl World {
     /// Constructs a new `World`.
-    pub fn new(objects: Vec<Sphere>, lights: Vec<PointLight>) -> Self {
-        Self { objects, lights }
+    pub fn new(objects: Vec<Sphere>, lights: Vec<impl Into<Light>>) -> Self {
+        let lights = lights.into_iter().map(Into::into).collect();
+        Self {
+            objects,
+            lights,
+            integrator: Integrator::default(),
+            fog: None,
+            fog_height: f32::INFINITY,
+            volumes: Vec::new(),
+            caustic_photons: 0,
+            caustic_radius: 0.0,
+            caustics: OnceLock::new(),
+            ambient_occlusion: None,
+        }
+    }
+
+    /// Fills the space around the objects with a participating medium.
+    /// The fog attenuates light exponentially with the distance travelled.
+    ///
+    /// Unless it is bounded with `with_fog_height()`, the fog extends
+    /// to infinity and swallows the light of directional lights entirely.
+    pub fn with_fog(mut self, fog: Medium) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    /// Confines the fog to the space below `height`, like a layer of
+    /// ground fog. Directional lights shining in from above only lose
+    /// the light absorbed and scattered on the way through the layer.
+    pub fn with_fog_height(mut self, height: f32) -> Self {
+        self.fog_height = height;
+        self
+    }
+
+    /// Adds a heterogeneous medium to the scene.
+    pub fn with_volume(mut self, volume: Volume) -> Self {
+        self.volumes.push(volume);
+        self
+    }
+
+    /// Traces `photons` photons from every light through the transparent
+    /// objects, so that the Whitted integrator renders the caustics they
+    /// cast. The caustics are estimated from the photons within `radius`
+    /// of the shaded points; a larger radius gives smoother but blurrier
+    /// caustics.
+    ///
+    /// The photons are traced when the first ray is shaded, so that they
+    /// see the world as it is rendered.
+    pub fn with_caustics(mut self, photons: u32, radius: f32) -> Self {
+        self.caustic_photons = photons;
+        self.caustic_radius = radius;
+        self.caustics = OnceLock::new();
+        self
+    }
+
+    /// Darkens the ambient term of the Whitted integrator by the fraction
+    /// of the surroundings of every hit which is occluded.
+    pub fn with_ambient_occlusion(mut self, ao: AmbientOcclusion) -> Self {
+        self.ambient_occlusion = Some(ao);
+        self
+    }
+
+    /// Sets how the colours of the rays are computed.
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
     }
 
     /// Intersects the ray with the sphere.
@@ -43,13 +138,374 @@ impl World {
     ///
     /// The necessary data is provided by `HitState`.
     pub fn shade_hit(&self, state: HitState<'_>) -> Color {
-        self.lights
+        self.shade_hit_sampled(state, &mut Sampler::default())
+    }
+
+    /// Returns the shade for an intersection, drawing the samples of the
+    /// area lights from `sampler`.
+    ///
+    /// The ambient term depends on the intensity of every light at the
+    /// point, see [`Light::ambient`], while only the light samples which
+    /// are not in shadow contribute to the diffuse and specular terms.
+    /// Shadow rays start at `over_point` to avoid self-intersection.
+    ///
+    /// Emissive objects add their own emission, and light the point like
+    /// area lights. With ambient occlusion, the ambient term is scaled by
+    /// the unoccluded fraction of the surroundings.
+    pub fn shade_hit_sampled(&self, state: HitState<'_>, sampler: &mut Sampler) -> Color {
+        let material = state.obj.material();
+        let emitted = if state.inside {
+            Color::BLACK
+        } else {
+            material.emission
+        };
+        // without an ambient term, there is nothing to occlude
+        let unoccluded = match &self.ambient_occlusion {
+            Some(ao) if material.ambient > 0.0 => ao.unoccluded(self, &state, sampler),
+            _ => 1.0,
+        };
+
+        let from_lights = self
+            .lights
             .iter()
             .map(|l| {
-                state
-                    .obj
-                    .material()
-                    .lighting(l, state.point, state.eyev, state.normal)
+                (
+                    None,
+                    l.ambient(state.point),
+                    l.samples(state.point, sampler),
+                )
+            })
+            .collect::<Vec<_>>();
+        let from_emitters = self
+            .emitters()
+            .filter(|obj| !std::ptr::eq(*obj, state.obj))
+            .map(|obj| {
+                let count = self.emitter_sample_count() as f32;
+                let samples: Vec<_> = self
+                    .emitter_samples(obj, state.point, sampler)
+                    .into_iter()
+                    .map(|(mut sample, pdf)| {
+                        sample.intensity = sample.intensity * (1.0 / (pdf * count));
+                        sample
+                    })
+                    .collect();
+                // an emitter has no intensity of its own, only what arrives
+                let intensity = samples
+                    .iter()
+                    .fold(Color::BLACK, |acc, s| acc + s.intensity);
+                (Some(obj), intensity, samples)
+            })
+            .collect::<Vec<_>>();
+
+        from_lights
+            .into_iter()
+            .chain(from_emitters)
+            .map(|(source, intensity, mut samples)| {
+                let intensity = intensity * unoccluded;
+                samples.retain_mut(|s| {
+                    let tr = self.transmittance_from(state.over_point, s, source, sampler);
+                    s.intensity = s.intensity.blend(tr);
+                    tr.max_component() > 0.0
+                });
+
+                material.lighting_samples(intensity, &samples, &state)
+            })
+            .fold(emitted, |acc, c| acc + c)
+    }
+
+    /// Returns the objects with an emissive material.
+    pub(crate) fn emitters(&self) -> impl Iterator<Item = &Sphere> {
+        self.objects
+            .iter()
+            .filter(|obj| obj.material().is_emissive() && obj.medium().is_none())
+    }
+
+    /// Returns the number of samples `emitter_samples()` takes on every
+    /// emissive object.
+    pub(crate) fn emitter_sample_count(&self) -> u32 {
+        EMITTER_GRID * EMITTER_GRID
+    }
+
+    /// Samples a jittered grid of points on the surface of the emissive
+    /// object `obj` as seen from `point`.
+    ///
+    /// Every sample carries the emitted radiance, together with the density
+    /// of its direction with respect to solid angle. Points on the far side
+    /// of the object are skipped.
+    pub(crate) fn emitter_samples(
+        &self,
+        obj: &Sphere,
+        point: Point3,
+        sampler: &mut Sampler,
+    ) -> Vec<(LightSample, f32)> {
+        let emission = obj.material().emission;
+        let steps = EMITTER_GRID as f32;
+
+        let mut samples = Vec::new();
+        for v in 0..EMITTER_GRID {
+            for u in 0..EMITTER_GRID {
+                let (ju, jv) = sampler.next_2d();
+                let pos = obj.point_on_surface((u as f32 + ju) / steps, (v as f32 + jv) / steps);
+
+                let sample = LightSample::towards(point, pos, emission);
+                let cos = -sample.lightv.dot(obj.normal_at(pos));
+                if cos <= 0.0 {
+                    continue;
+                }
+
+                let pdf = obj.surface_pdf(pos) * sample.distance * sample.distance / cos;
+                samples.push((sample, pdf));
+            }
+        }
+
+        samples
+    }
+
+    /// Returns `true` if an object lies between `point` and the light sample.
+    pub fn is_shadowed(&self, point: Point3, sample: &LightSample) -> bool {
+        self.is_shadowed_from(point, sample, None)
+    }
+
+    /// Returns `true` if an object other than `source`, the object emitting
+    /// the sample, lies between `point` and the sample.
+    fn is_shadowed_from(
+        &self,
+        point: Point3,
+        sample: &LightSample,
+        source: Option<&Sphere>,
+    ) -> bool {
+        let r = Ray::new(point, sample.lightv);
+        let xs = self.intersect(&r);
+
+        xs.into_inner().iter().any(|h| {
+            h.t >= 0.0
+                && h.t < sample.distance
+                && h.obj.medium().is_none()
+                && !source.is_some_and(|obj| std::ptr::eq(obj, h.obj))
+        })
+    }
+
+    /// Returns the fraction of the light of the sample which reaches
+    /// `point`. This is black if an object lies in between, and otherwise
+    /// the transmittance of the media along the way. The transmittance of
+    /// the volumes is estimated by ratio tracking, drawing samples from
+    /// `sampler`.
+    pub fn transmittance(
+        &self,
+        point: Point3,
+        sample: &LightSample,
+        sampler: &mut Sampler,
+    ) -> Color {
+        self.transmittance_from(point, sample, None, sampler)
+    }
+
+    /// Returns the transmittance like `transmittance()` for a sample of
+    /// the emissive object `source`, which does not shadow itself.
+    pub(crate) fn transmittance_from(
+        &self,
+        point: Point3,
+        sample: &LightSample,
+        source: Option<&Sphere>,
+        sampler: &mut Sampler,
+    ) -> Color {
+        if self.is_shadowed_from(point, sample, source) {
+            return Color::BLACK;
+        }
+
+        let r = Ray::new(point, sample.lightv);
+        let homogeneous = self
+            .media_segments(&r, sample.distance)
+            .into_iter()
+            .fold(Color::WHITE, |acc, (t0, t1, medium)| {
+                acc.blend(medium.transmittance(t1 - t0))
+            });
+
+        self.volumes.iter().fold(homogeneous, |acc, v| {
+            acc.blend(v.transmittance(&r, sample.distance, sampler))
+        })
+    }
+
+    /// Returns `true` if the scene contains any participating media.
+    pub(crate) fn has_media(&self) -> bool {
+        self.fog.is_some()
+            || !self.volumes.is_empty()
+            || self.objects.iter().any(|obj| obj.medium().is_some())
+    }
+
+    /// Returns the medium at `point`, i.e. the fog combined with the media
+    /// of all the objects containing the point.
+    pub(crate) fn medium_at(&self, point: Point3) -> Option<Medium> {
+        let fog = self.fog.clone().filter(|_| point.y() < self.fog_height);
+        self.objects
+            .iter()
+            .filter(|obj| obj.contains(point))
+            .filter_map(|obj| obj.medium())
+            .fold(fog, |acc, m| match acc {
+                Some(acc) => Some(acc.combine(m)),
+                None => Some(m.clone()),
+            })
+    }
+
+    /// Returns the medium at `point` like `medium_at()`, but also including
+    /// the volumes, whose coefficients vary from point to point.
+    pub(crate) fn local_medium(&self, point: Point3) -> Option<Medium> {
+        self.volumes.iter().filter_map(|v| v.medium_at(point)).fold(
+            self.medium_at(point),
+            |acc, m| match acc {
+                Some(acc) => Some(acc.combine(&m)),
+                None => Some(m),
+            },
+        )
+    }
+
+    /// Splits the ray up to `t_max` into segments at the boundaries of the
+    /// media, including the top of the fog. Returns the start, the end and
+    /// the medium of every segment which lies inside a medium. Volumes are
+    /// not included.
+    pub(crate) fn media_segments(&self, r: &Ray, t_max: f32) -> Vec<(f32, f32, Medium)> {
+        if !self.has_media() {
+            return Vec::new();
+        }
+
+        let fog_top = (self.fog_height - r.orig.y()) / r.dir.y();
+        let mut ts: Vec<f32> = self
+            .objects
+            .iter()
+            .filter(|obj| obj.medium().is_some())
+            .flat_map(|obj| obj.intersect(r).into_inner())
+            .map(|h| h.t)
+            .chain([fog_top])
+            .filter(|&t| t > 0.0 && t < t_max)
+            .chain([0.0, t_max])
+            .collect();
+        ts.sort_unstable_by(f32::total_cmp);
+
+        ts.windows(2)
+            .filter(|w| w[1] > w[0])
+            .filter_map(|w| {
+                let mid = if w[1].is_finite() {
+                    0.5 * (w[0] + w[1])
+                } else {
+                    w[0] + 1.0
+                };
+                self.medium_at(r.pos(mid)).map(|m| (w[0], w[1], m))
+            })
+            .collect()
+    }
+
+    /// Splits the ray up to `t_max` into pieces at the boundaries of all the
+    /// media, including the volumes. Returns the start and the end of every
+    /// piece which lies inside a medium, along with an upper bound of the
+    /// extinction coefficient in the piece.
+    pub(crate) fn media_intervals(&self, r: &Ray, t_max: f32) -> Vec<(f32, f32, f32)> {
+        let segments = self.media_segments(r, t_max);
+        let volumes: Vec<(f32, f32, f32)> = self
+            .volumes
+            .iter()
+            .filter_map(|v| {
+                let (t0, t1) = v.interval(r, t_max)?;
+                Some((t0, t1, v.majorant()))
+            })
+            .collect();
+
+        let mut ts: Vec<f32> = segments
+            .iter()
+            .flat_map(|(t0, t1, _)| [*t0, *t1])
+            .chain(volumes.iter().flat_map(|(t0, t1, _)| [*t0, *t1]))
+            .collect();
+        ts.sort_unstable_by(f32::total_cmp);
+        ts.dedup();
+
+        ts.windows(2)
+            .filter_map(|w| {
+                let mid = if w[1].is_finite() {
+                    0.5 * (w[0] + w[1])
+                } else {
+                    w[0] + 1.0
+                };
+                let segment = segments.iter().find(|(t0, t1, _)| *t0 <= mid && mid < *t1);
+                let inside: Vec<f32> = volumes
+                    .iter()
+                    .filter(|(t0, t1, _)| *t0 <= mid && mid < *t1)
+                    .map(|(_, _, majorant)| *majorant)
+                    .collect();
+                if segment.is_none() && inside.is_empty() {
+                    return None;
+                }
+
+                let majorant = segment.map_or(0.0, |(_, _, m)| m.sigma_t().max_component())
+                    + inside.iter().sum::<f32>();
+                Some((w[0], w[1], majorant))
+            })
+            .collect()
+    }
+
+    /// Attenuates the `color` arriving at the ray from `t_end` by the media
+    /// along the ray, and adds the light scattered towards the origin of
+    /// the ray. The media are integrated by ray marching with jittered
+    /// steps, only accounting for light which is scattered once.
+    fn march(&self, r: &Ray, t_end: f32, color: Color, sampler: &mut Sampler) -> Color {
+        let speed = r.dir.mag();
+        let eyev = -r.dir / speed;
+
+        let mut tr = Color::WHITE;
+        let mut scattered = Color::BLACK;
+        for (t0, t1, _) in self.media_intervals(r, t_end) {
+            let t1 = if t1.is_finite() {
+                t1
+            } else {
+                // stop marching once almost no light makes it back
+                let sigma = self
+                    .local_medium(r.pos(t0 + 1.0))
+                    .map_or([0.0; 3], |m| m.sigma_t().into_inner().0);
+                let min = sigma
+                    .into_iter()
+                    .filter(|&s| s > 0.0)
+                    .fold(f32::INFINITY, f32::min);
+                if min.is_infinite() {
+                    continue;
+                }
+                t0 + 1000f32.ln() / (min * speed)
+            };
+
+            let dt = (t1 - t0) / MARCH_STEPS as f32;
+            for i in 0..MARCH_STEPS {
+                let t = t0 + (i as f32 + sampler.next_f32()) * dt;
+                let point = r.pos(t);
+                let medium = match self.local_medium(point) {
+                    Some(medium) => medium,
+                    None => continue,
+                };
+                let inscattered = self.in_scattered(point, eyev, &medium, sampler);
+
+                scattered = scattered + tr.blend(medium.sigma_s).blend(inscattered) * (dt * speed);
+                tr = tr.blend(medium.transmittance(dt * speed));
+            }
+        }
+
+        tr.blend(color) + scattered
+    }
+
+    /// Returns the light of all the light sources arriving at `point`,
+    /// weighted by the phase function for scattering along `eyev`.
+    fn in_scattered(
+        &self,
+        point: Point3,
+        eyev: Vec3,
+        medium: &Medium,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let samples: Vec<LightSample> = self
+            .lights
+            .iter()
+            .flat_map(|l| l.samples(point, sampler))
+            .collect();
+
+        samples
+            .iter()
+            .map(|s| {
+                s.intensity.blend(self.transmittance(point, s, sampler))
+                    * medium.phase(s.lightv, eyev)
             })
             .fold(Color::BLACK, |acc, c| acc + c)
     }
@@ -57,13 +513,113 @@ impl World {
     /// Intersects the world with the given ray and returns the colour
     /// at the resulting intersection.
     pub fn color_at(&self, r: &Ray) -> Color {
+        self.color_at_sampled(r, &mut Sampler::default())
+    }
+
+    /// Intersects the world with the given ray and returns the colour
+    /// at the resulting intersection, drawing random samples from `sampler`.
+    ///
+    /// The colour is computed by the integrator of the world.
+    pub fn color_at_sampled(&self, r: &Ray, sampler: &mut Sampler) -> Color {
+        match &self.integrator {
+            Integrator::Whitted => (),
+            Integrator::PathTracer(pt) => return pt.radiance(self, r, sampler),
+            Integrator::AmbientOcclusion(ao) => return ao.radiance(self, r, sampler),
+        }
+
+        self.trace(r, MAX_BOUNCES, sampler)
+    }
+
+    /// Returns the light passing through a transparent surface towards the
+    /// eye. Part of it is reflected off the surface instead, as given by
+    /// the Fresnel equations.
+    fn transmitted(&self, state: &HitState<'_>, remaining: u32, sampler: &mut Sampler) -> Color {
+        let transparency = state.obj.material().transparency;
+        if transparency <= 0.0 || remaining == 0 {
+            return Color::BLACK;
+        }
+
+        let reflected = self.trace(
+            &Ray::new(state.over_point, state.reflectv()),
+            remaining - 1,
+            sampler,
+        );
+        let color = match state.refractv() {
+            Some(dir) => {
+                let refracted =
+                    self.trace(&Ray::new(state.under_point, dir), remaining - 1, sampler);
+                let reflectance = state.schlick();
+                reflected * reflectance + refracted * (1.0 - reflectance)
+            }
+            None => reflected,
+        };
+
+        color * transparency
+    }
+
+    /// Returns the photon map of the caustics, tracing the photons on the
+    /// first call. Returns `None` if the world has no caustics.
+    pub(crate) fn caustic_map(&self) -> Option<&PhotonMap> {
+        if self.caustic_photons == 0 {
+            return None;
+        }
+
+        Some(self.caustics.get_or_init(|| {
+            photon_map::trace_caustics(self, self.caustic_photons, &mut Sampler::default())
+        }))
+    }
+
+    /// Returns the light focused onto the hit by transparent objects,
+    /// estimated from the density of the photons around it.
+    fn caustics_at(&self, state: &HitState<'_>) -> Color {
+        let map = match self.caustic_map() {
+            Some(map) => map,
+            None => return Color::BLACK,
+        };
+
+        let area = PI * self.caustic_radius * self.caustic_radius;
+        let samples: Vec<LightSample> = map
+            .within(state.point, self.caustic_radius)
+            .into_iter()
+            .filter_map(|photon| {
+                let cos = photon.lightv.dot(state.normal);
+                (cos > 0.0).then(|| LightSample {
+                    lightv: photon.lightv,
+                    distance: 0.0,
+                    intensity: photon.power * (1.0 / (area * cos)),
+                })
+            })
+            .collect();
+
+        state
+            .obj
+            .material()
+            .lighting_samples(Color::BLACK, &samples, state)
+    }
+
+    /// Returns the colour seen along the ray by the Whitted integrator,
+    /// following at most `remaining` bounces off transparent objects.
+    fn trace(&self, r: &Ray, remaining: u32, sampler: &mut Sampler) -> Color {
         let mut xs = self.intersect(r);
+        let (t_end, color) = match xs.prepare_hit(r) {
+            Some(state) => {
+                let t = state.t;
+                let absorbed = state.within.map_or(Color::WHITE, |obj| {
+                    obj.material().attenuation(state.t * r.dir.mag())
+                });
+                let transmitted = self.transmitted(&state, remaining, sampler);
+                let caustics = self.caustics_at(&state);
+                let color = self.shade_hit_sampled(state, sampler) + transmitted + caustics;
+
+                (t, color.blend(absorbed))
+            }
+            None => (f32::INFINITY, Color::BLACK),
+        };
 
-        if let Some(hit) = xs.hit() {
-            let state = hit.prepare_computations(r);
-            self.shade_hit(state)
+        if self.has_media() {
+            self.march(r, t_end, color, sampler)
         } else {
-            Color::BLACK
+            color
         }
     }
 }